@@ -0,0 +1,310 @@
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use chrono::prelude::Local;
+
+use crate::{config, schema::parse_database, LocalError};
+
+/// How many backups are kept, older ones are deleted when a new one is made
+const MAX_BACKUPS: usize = 5;
+
+/// Timestamp in backup file names, sorts the same way as a string and as a date
+const BACKUP_TIME_FORMAT: &str = "%Y%m%dT%H%M%S%3f";
+
+/// Length of a `BACKUP_TIME_FORMAT` timestamp, `20240501T101500123`
+const BACKUP_TIME_LEN: usize = 18;
+
+/// Makes temporary file names unique within this process
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Write to a temporary file next to `path`, flush it to disk and rename it over the original.
+///
+/// The rename is atomic and only happens once the data is on disk, so a crash or a full disk
+/// leaves either the old or the new file behind, never a truncated one. The temporary file is
+/// named after the process and a counter, so the CLI and a running TUI never share one.
+pub fn write_atomically(path: &Path, content: &[u8]) -> Result<(), LocalError> {
+    let mut temp_name = path.as_os_str().to_owned();
+    temp_name.push(format!(
+        ".{}-{}.tmp",
+        process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let temp_path = PathBuf::from(temp_name);
+
+    let written = File::create(&temp_path).and_then(|mut file| {
+        file.write_all(content)?;
+        file.sync_all()
+    });
+
+    if let Err(e) = written {
+        let _ = fs::remove_file(&temp_path);
+        return Err(LocalError::from_write(&temp_path, e));
+    }
+
+    fs::rename(&temp_path, path).map_err(|e| {
+        let _ = fs::remove_file(&temp_path);
+        LocalError::from_write(path, e)
+    })?;
+
+    sync_parent_dir(path)
+}
+
+/// Flush the directory entry of a renamed file, without it the rename itself can be lost
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) -> Result<(), LocalError> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    File::open(parent)
+        .and_then(|dir| dir.sync_all())
+        .map_err(|e| LocalError::from_write(parent, e))
+}
+
+/// Directories cannot be opened for syncing here, the rename is as durable as it gets
+#[cfg(not(unix))]
+fn sync_parent_dir(_path: &Path) -> Result<(), LocalError> {
+    Ok(())
+}
+
+/// `backups` directory next to the database
+fn backup_dir(db_path: &Path) -> PathBuf {
+    db_path
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join("backups")
+}
+
+/// File name stem shared by all backups of a database, the stem of the database itself
+fn backup_stem(db_path: &Path) -> String {
+    db_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "db".to_string())
+}
+
+/// Whether a file name is `<stem>-<timestamp>.json`. A database called `db` must not pick up
+/// the backups of one called `db-work`, so the timestamp has to be exactly what follows.
+fn is_backup_of(stem: &str, file_name: &str) -> bool {
+    let timestamp = file_name
+        .strip_prefix(stem)
+        .and_then(|rest| rest.strip_prefix('-'))
+        .and_then(|rest| rest.strip_suffix(".json"));
+
+    match timestamp {
+        Some(timestamp) => {
+            timestamp.len() == BACKUP_TIME_LEN
+                && timestamp.char_indices().all(|(index, c)| match index {
+                    8 => c == 'T',
+                    _ => c.is_ascii_digit(),
+                })
+        }
+        None => false,
+    }
+}
+
+/// Copy the database into a new timestamped backup and drop the oldest ones beyond `MAX_BACKUPS`.
+/// Only call this with a database that was just read successfully so every backup is a good one.
+pub fn create_backup() -> Result<(), LocalError> {
+    create_backup_of(config::db_path())
+}
+
+fn create_backup_of(db_path: &Path) -> Result<(), LocalError> {
+    let dir = backup_dir(db_path);
+    fs::create_dir_all(&dir).map_err(|e| LocalError::from_write(&dir, e))?;
+
+    let backup_path = dir.join(format!(
+        "{}-{}.json",
+        backup_stem(db_path),
+        Local::now().format(BACKUP_TIME_FORMAT)
+    ));
+
+    let content = fs::read(db_path).map_err(|e| LocalError::from_read(db_path, e))?;
+    write_atomically(&backup_path, &content)?;
+
+    for old in list_backups(db_path).into_iter().skip(MAX_BACKUPS) {
+        fs::remove_file(&old).map_err(|e| LocalError::from_write(&old, e))?;
+    }
+
+    Ok(())
+}
+
+/// All backups of a database, newest first
+fn list_backups(db_path: &Path) -> Vec<PathBuf> {
+    let stem = backup_stem(db_path);
+
+    let mut backups: Vec<PathBuf> = fs::read_dir(backup_dir(db_path))
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    path.file_name()
+                        .map(|name| is_backup_of(&stem, &name.to_string_lossy()))
+                        .unwrap_or(false)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    backups.sort();
+    backups.reverse();
+
+    backups
+}
+
+/// Replace a database that cannot be parsed with the newest backup that can.
+///
+/// The broken file is kept next to the database with a `.broken` suffix so nothing is lost.
+pub fn restore_latest_backup() -> Result<PathBuf, LocalError> {
+    restore_latest_backup_of(config::db_path())
+}
+
+fn restore_latest_backup_of(db_path: &Path) -> Result<PathBuf, LocalError> {
+    let backup = list_backups(db_path)
+        .into_iter()
+        .find(|path| {
+            fs::read_to_string(path)
+                .ok()
+                .and_then(|raw| parse_database(path, &raw).ok())
+                .is_some()
+        })
+        .ok_or(LocalError::NoBackup)?;
+
+    let content = fs::read(&backup).map_err(|e| LocalError::from_read(&backup, e))?;
+
+    if db_path.exists() {
+        let mut broken_name = db_path.as_os_str().to_owned();
+        broken_name.push(format!(
+            ".broken-{}",
+            Local::now().format(BACKUP_TIME_FORMAT)
+        ));
+
+        fs::rename(db_path, &broken_name)
+            .map_err(|e| LocalError::from_write(Path::new(&broken_name), e))?;
+    }
+
+    write_atomically(db_path, &content)?;
+
+    Ok(backup)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+    use crate::schema::serialize_database;
+
+    /// A database in a directory of its own under the system temp directory
+    fn temp_db(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("toodles-backup-{}-{}", process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("backups")).unwrap();
+
+        let path = dir.join("db.json");
+        fs::write(&path, serialize_database(&[], &[])).unwrap();
+        path
+    }
+
+    /// Put a backup with the given timestamp into the backup directory
+    fn old_backup(db_path: &Path, name: &str, content: &[u8]) -> PathBuf {
+        let path = backup_dir(db_path).join(name);
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn file_names(paths: &[PathBuf]) -> Vec<String> {
+        paths
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn backup_names_need_the_exact_stem_and_a_timestamp() {
+        assert!(is_backup_of("db", "db-20240501T101500123.json"));
+        assert!(!is_backup_of("db", "db-work-20240501T101500123.json"));
+        assert!(!is_backup_of("db", "db-20240501.json"));
+        assert!(!is_backup_of("db", "db-20240501X101500123.json"));
+        assert!(!is_backup_of("db", "db-20240501T101500123.json.tmp"));
+    }
+
+    #[test]
+    fn rotation_keeps_the_newest_backups() {
+        let db = temp_db("rotation");
+        for day in 1..=MAX_BACKUPS {
+            old_backup(&db, &format!("db-202405{:02}T000000000.json", day), b"{}");
+        }
+
+        create_backup_of(&db).unwrap();
+
+        let backups = list_backups(&db);
+        assert_eq!(backups.len(), MAX_BACKUPS);
+        // the new one is first, the oldest one is gone
+        assert_eq!(fs::read(&backups[0]).unwrap(), serialize_database(&[], &[]));
+        assert!(!file_names(&backups).contains(&"db-20240501T000000000.json".to_string()));
+    }
+
+    #[test]
+    fn rotation_leaves_sibling_databases_alone() {
+        let db = temp_db("siblings");
+        let sibling = old_backup(&db, "db-work-20200101T000000000.json", b"{}");
+        for day in 1..=MAX_BACKUPS {
+            old_backup(&db, &format!("db-202405{:02}T000000000.json", day), b"{}");
+        }
+
+        create_backup_of(&db).unwrap();
+
+        assert!(sibling.exists());
+        assert!(!list_backups(&db).contains(&sibling));
+    }
+
+    #[test]
+    fn restore_takes_the_newest_readable_backup() {
+        let db = temp_db("restore");
+        let good = serialize_database(&[], &[]);
+        old_backup(&db, "db-20240501T000000000.json", &good);
+        old_backup(&db, "db-20240502T000000000.json", b"not json");
+        // newer, but it belongs to another database
+        old_backup(&db, "db-work-20240503T000000000.json", &good);
+        fs::write(&db, "broken").unwrap();
+
+        let restored = restore_latest_backup_of(&db).unwrap();
+
+        assert_eq!(file_names(&[restored]), vec!["db-20240501T000000000.json"]);
+        assert_eq!(fs::read(&db).unwrap(), good);
+
+        let kept_broken = fs::read_dir(db.parent().unwrap())
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .any(|entry| {
+                entry
+                    .file_name()
+                    .to_string_lossy()
+                    .starts_with("db.json.broken-")
+            });
+        assert!(kept_broken);
+    }
+
+    #[test]
+    fn atomic_writes_leave_no_temporary_files() {
+        let db = temp_db("atomic");
+
+        write_atomically(&db, b"new").unwrap();
+
+        assert_eq!(fs::read_to_string(&db).unwrap(), "new");
+        let leftovers = fs::read_dir(db.parent().unwrap())
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().ends_with(".tmp"))
+            .count();
+        assert_eq!(leftovers, 0);
+    }
+}
@@ -0,0 +1,79 @@
+use crate::ChecklistItem;
+
+/// Parse the checklist typed into the task form, one item per line.
+///
+/// A line starting with `[x]` is a done item, `[ ]` or no marker an open one. Empty lines are
+/// skipped.
+pub fn parse_checklist(input: &str) -> Vec<ChecklistItem> {
+    input
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let (done, text) = if let Some(text) = line
+                .strip_prefix("[x]")
+                .or_else(|| line.strip_prefix("[X]"))
+            {
+                (true, text)
+            } else {
+                (false, line.strip_prefix("[ ]").unwrap_or(line))
+            };
+
+            ChecklistItem {
+                text: text.trim().to_string(),
+                done,
+            }
+        })
+        .filter(|item| !item.text.is_empty())
+        .collect()
+}
+
+/// The checklist as it is pre-filled in the edit form, the reverse of `parse_checklist`
+pub fn format_checklist(items: &[ChecklistItem]) -> String {
+    items
+        .iter()
+        .map(|item| format!("{} {}", checkbox(item.done), item.text))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn checkbox(done: bool) -> &'static str {
+    if done {
+        "[x]"
+    } else {
+        "[ ]"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markers_set_the_done_state() {
+        let items = parse_checklist("[x] buy milk\n[ ] eggs\nbread\n[X] butter");
+
+        let parsed: Vec<_> = items.iter().map(|i| (i.text.as_str(), i.done)).collect();
+        assert_eq!(
+            parsed,
+            vec![
+                ("buy milk", true),
+                ("eggs", false),
+                ("bread", false),
+                ("butter", true)
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_lines_and_items_are_skipped() {
+        assert!(parse_checklist("\n  \n[ ]\n[x]  ").is_empty());
+    }
+
+    #[test]
+    fn formatting_round_trips() {
+        let input = "[x] one\n[ ] two";
+
+        assert_eq!(format_checklist(&parse_checklist(input)), input);
+    }
+}
@@ -0,0 +1,598 @@
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::prelude::Local;
+
+use crate::{
+    backup,
+    checklist::checkbox,
+    due::DUE_FORMAT,
+    export::{export, export_to_file, ExportFormat},
+    import::{self, dedupe_by_name, ImportFormat, Parsed},
+    store::TaskStore,
+    tags::format_tags,
+    view::ListView,
+    LocalError, MenuItem, Task,
+};
+
+pub const USAGE: &str = "\
+Usage:
+  toodles [--db <path>]                      open the interactive UI
+  toodles [--db <path>] <command>            run a command on the given database
+  toodles add <name> [-p <priority>] [-d <description>]
+  toodles list [--done | --all] [--json]     open tasks unless --done or --all is given
+  toodles done <id>                          mark a task as done
+  toodles rm <id>                            delete a task
+  toodles show <id>                          print everything about a task
+  toodles export <markdown|csv|todotxt> [--open | --done] [-o <file>]
+                                             all tasks unless --open or --done is given,
+                                             written to stdout unless -o is given
+  toodles import <file> [--format <markdown|csv|todotxt>] [--dedupe] [--dry-run] [--yes]
+                                             format guessed from the extension, --dedupe skips
+                                             names that exist, asks before importing unless --yes
+
+Commands can run while the interactive UI has the same database open. The UI reads their
+changes within a second. A change made in the UI before it read them is not written over
+them; the two are merged and that is reported.";
+
+/// A non-interactive command given on the command line
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    Add {
+        name: String,
+        priority: usize,
+        description: String,
+    },
+    List {
+        tab: MenuItem,
+        json: bool,
+    },
+    Done(usize),
+    Remove(usize),
+    Show(usize),
+    Export {
+        format: ExportFormat,
+        tab: MenuItem,
+        output: Option<PathBuf>,
+    },
+    Import {
+        path: PathBuf,
+        format: Option<ImportFormat>,
+        dedupe: bool,
+        dry_run: bool,
+        yes: bool,
+    },
+    Help,
+}
+
+/// What the command line asks for
+#[derive(Debug, PartialEq, Eq)]
+pub struct Args {
+    /// Database given with `--db`
+    pub db: Option<PathBuf>,
+    /// None when no command is given and the UI should start
+    pub command: Option<Command>,
+}
+
+/// Parse the command line. `--db` is only an option before the command, everything after the
+/// command is left to it, so a task named `--db` is not taken for the flag.
+pub fn parse_args(args: &[String]) -> Result<Args, String> {
+    let mut db = None;
+    let mut args = args.iter();
+
+    let command = loop {
+        let arg = match args.next() {
+            Some(arg) => arg,
+            None => return Ok(Args { db, command: None }),
+        };
+
+        let path = match arg.strip_prefix("--db=") {
+            Some(path) => path,
+            None if arg == "--db" => args.next().map(String::as_str).unwrap_or_default(),
+            None => break arg.as_str(),
+        };
+
+        // without a path the default database would be opened without a word of warning
+        if path.is_empty() {
+            return Err("--db needs a path".to_string());
+        }
+
+        db = Some(PathBuf::from(path));
+    };
+    let rest: Vec<&str> = args.map(String::as_str).collect();
+
+    let command = match command {
+        "add" => parse_add(&rest)?,
+        "list" | "ls" => parse_list(&rest)?,
+        "done" => Command::Done(parse_id(&rest)?),
+        "rm" => Command::Remove(parse_id(&rest)?),
+        "show" => Command::Show(parse_id(&rest)?),
+        "export" => parse_export(&rest)?,
+        "import" => parse_import(&rest)?,
+        "help" | "-h" | "--help" => Command::Help,
+        other => return Err(format!("Unknown command '{}'", other)),
+    };
+
+    Ok(Args {
+        db,
+        command: Some(command),
+    })
+}
+
+fn parse_add(args: &[&str]) -> Result<Command, String> {
+    let mut name = None;
+    let mut priority = 0;
+    let mut description = String::new();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match *arg {
+            "-p" | "--priority" => {
+                let value = args.next().ok_or("-p needs a priority")?;
+                priority = value
+                    .parse()
+                    .map_err(|_| format!("Priority '{}' is not a number", value))?;
+            }
+            "-d" | "--description" => {
+                description = args.next().ok_or("-d needs a description")?.to_string();
+            }
+            flag if flag.starts_with('-') && flag.len() > 1 => {
+                return Err(format!("Unknown option '{}' for add", flag))
+            }
+            word if name.is_none() => name = Some(word.to_string()),
+            word => return Err(format!("Unexpected '{}', quote names with spaces", word)),
+        }
+    }
+
+    let name = name
+        .filter(|name| !name.trim().is_empty())
+        .ok_or("add needs the name of the task")?;
+
+    Ok(Command::Add {
+        name: name.trim().to_string(),
+        priority,
+        description,
+    })
+}
+
+fn parse_list(args: &[&str]) -> Result<Command, String> {
+    let mut tab = MenuItem::Todo;
+    let mut json = false;
+
+    for arg in args {
+        match *arg {
+            "--done" => tab = MenuItem::Done,
+            "--all" => tab = MenuItem::All,
+            "--json" => json = true,
+            other => return Err(format!("Unknown option '{}' for list", other)),
+        }
+    }
+
+    Ok(Command::List { tab, json })
+}
+
+fn parse_export(args: &[&str]) -> Result<Command, String> {
+    let (format, args) = args
+        .split_first()
+        .ok_or("export needs a format: markdown, csv or todotxt")?;
+    let format = ExportFormat::from_name(format)
+        .ok_or_else(|| format!("Unknown export format '{}'", format))?;
+
+    let mut tab = MenuItem::All;
+    let mut output = None;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match *arg {
+            "--open" => tab = MenuItem::Todo,
+            "--done" => tab = MenuItem::Done,
+            "-o" | "--output" => {
+                output = Some(PathBuf::from(args.next().ok_or("-o needs a file")?));
+            }
+            other => return Err(format!("Unknown option '{}' for export", other)),
+        }
+    }
+
+    Ok(Command::Export {
+        format,
+        tab,
+        output,
+    })
+}
+
+fn parse_import(args: &[&str]) -> Result<Command, String> {
+    let mut path = None;
+    let mut format = None;
+    let mut dedupe = false;
+    let mut dry_run = false;
+    let mut yes = false;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match *arg {
+            "--format" | "-f" => {
+                let name = args.next().ok_or("--format needs a format")?;
+                format = Some(
+                    ImportFormat::from_name(name)
+                        .ok_or_else(|| format!("Unknown import format '{}'", name))?,
+                );
+            }
+            "--dedupe" => dedupe = true,
+            "--dry-run" | "-n" => dry_run = true,
+            "--yes" | "-y" => yes = true,
+            flag if flag.starts_with('-') && flag.len() > 1 => {
+                return Err(format!("Unknown option '{}' for import", flag))
+            }
+            file if path.is_none() => path = Some(PathBuf::from(file)),
+            other => return Err(format!("Unexpected '{}' after the file", other)),
+        }
+    }
+
+    let path = path.ok_or("import needs the file to read")?;
+
+    if format.is_none() && ImportFormat::from_path(&path).is_none() {
+        return Err(format!(
+            "Cannot tell the format of {}, give it with --format",
+            path.display()
+        ));
+    }
+
+    Ok(Command::Import {
+        path,
+        format,
+        dedupe,
+        dry_run,
+        yes,
+    })
+}
+
+fn parse_id(args: &[&str]) -> Result<usize, String> {
+    match args {
+        [id] => id.parse().map_err(|_| format!("'{}' is not a task ID", id)),
+        [] => Err("A task ID is needed".to_string()),
+        [_, extra, ..] => Err(format!("Unexpected '{}' after the task ID", extra)),
+    }
+}
+
+/// Run a command against the database and print the result to stdout
+pub fn run(command: Command) -> Result<(), LocalError> {
+    if command == Command::Help {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+
+    let mut store = TaskStore::load()?;
+
+    match command {
+        Command::Add {
+            name,
+            priority,
+            description,
+        } => {
+            let task = Task {
+                name,
+                priority,
+                description,
+                created: Local::now(),
+                ..Task::default()
+            };
+
+            backup::create_backup()?;
+            let id = store.add(task)?;
+            println!("Added task {}", id);
+        }
+        Command::List { tab, json } => {
+            let tasks = store.tasks_for_tab(tab, &ListView::default());
+
+            if json {
+                // serialising plain structs with string keys cannot fail
+                let output = serde_json::to_string_pretty(&tasks).expect("Tasks serialise to JSON");
+                println!("{}", output);
+            } else {
+                for task in tasks {
+                    println!("{}", summary_line(task));
+                }
+            }
+        }
+        Command::Done(id) => {
+            let task = store.find(id).ok_or(LocalError::TaskNotFound(id))?;
+
+            if task.complete {
+                println!("Task {} is already done", id);
+            } else {
+                backup::create_backup()?;
+                store.toggle_complete(id)?;
+                println!("Marked task {} as done", id);
+            }
+        }
+        Command::Remove(id) => {
+            store.find(id).ok_or(LocalError::TaskNotFound(id))?;
+
+            backup::create_backup()?;
+            let task = store.remove(id)?;
+            println!("Deleted task {} '{}'", id, task.name);
+        }
+        Command::Show(id) => {
+            let task = store.find(id).ok_or(LocalError::TaskNotFound(id))?;
+            print_task(task);
+        }
+        Command::Export {
+            format,
+            tab,
+            output,
+        } => {
+            let tasks = store.tasks_for_tab(tab, &ListView::default());
+
+            match output {
+                Some(path) => {
+                    export_to_file(&tasks, format, &path)?;
+                    println!("Exported {} tasks to {}", tasks.len(), path.display());
+                }
+                None => print!("{}", export(&tasks, format)),
+            }
+        }
+        Command::Import {
+            path,
+            format,
+            dedupe,
+            dry_run,
+            yes,
+        } => {
+            let format = format
+                .or_else(|| ImportFormat::from_path(&path))
+                .expect("The format is checked when parsing the arguments");
+            let content = fs::read_to_string(&path).map_err(|e| LocalError::from_read(&path, e))?;
+
+            let Parsed {
+                mut tasks,
+                problems,
+            } = import::parse(&content, format);
+
+            let duplicates = match dedupe {
+                true => dedupe_by_name(
+                    &mut tasks,
+                    store
+                        .tasks_for_tab(MenuItem::All, &ListView::default())
+                        .into_iter(),
+                ),
+                false => Vec::new(),
+            };
+
+            print_import_summary(&path, &tasks, &duplicates, &problems);
+
+            if dry_run || tasks.is_empty() || !(yes || confirm("Import these tasks?")) {
+                println!("Nothing was imported");
+                return Ok(());
+            }
+
+            backup::create_backup()?;
+            for task in tasks {
+                store.add(task)?;
+            }
+            println!("Imported");
+        }
+        Command::Help => unreachable!(),
+    }
+
+    store.flush_on_exit()
+}
+
+/// One line per task for `list`
+fn summary_line(task: &Task) -> String {
+    format!("{:>4} {}", task.id, task_line(task))
+}
+
+/// State, priority, name, due date and tags of a task on one line
+fn task_line(task: &Task) -> String {
+    let mut line = format!(
+        "{} p{} {}",
+        checkbox(task.complete),
+        task.priority,
+        task.name
+    );
+
+    if let Some(due) = task.due {
+        line.push_str(&format!("  (due {})", due.format(DUE_FORMAT)));
+    }
+
+    if !task.tags.is_empty() {
+        line.push_str(&format!("  {}", format_tags(&task.tags)));
+    }
+
+    line
+}
+
+/// What an import would add, printed before anything is written
+fn print_import_summary(path: &Path, tasks: &[Task], duplicates: &[String], problems: &[String]) {
+    let done = tasks.iter().filter(|task| task.complete).count();
+
+    println!(
+        "{}: {} tasks to import ({} open, {} done)",
+        path.display(),
+        tasks.len(),
+        tasks.len() - done,
+        done
+    );
+
+    for task in tasks {
+        println!("  + {}", task_line(task));
+    }
+
+    if !duplicates.is_empty() {
+        println!(
+            "{} skipped, a task with that name exists:",
+            duplicates.len()
+        );
+        for name in duplicates {
+            println!("  = {}", name);
+        }
+    }
+
+    if !problems.is_empty() {
+        println!("{} lines could not be read:", problems.len());
+        for problem in problems {
+            println!("  ! {}", problem);
+        }
+    }
+}
+
+/// Ask a yes / no question on the terminal, anything but yes counts as no
+fn confirm(question: &str) -> bool {
+    print!("{} [y/N] ", question);
+    let _ = io::stdout().flush();
+
+    let mut answer = String::new();
+    if io::stdin().lock().read_line(&mut answer).is_err() {
+        return false;
+    }
+
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+fn print_task(task: &Task) {
+    let date = |date: Option<chrono::DateTime<Local>>| {
+        date.map(|date| date.format(DUE_FORMAT).to_string())
+            .unwrap_or_else(|| "-".to_string())
+    };
+
+    println!("ID:        {}", task.id);
+    println!("Name:      {}", task.name);
+    println!("Complete:  {}", task.complete);
+    println!("Priority:  {}", task.priority);
+    println!("Created:   {}", task.created.format(DUE_FORMAT));
+    println!("Due:       {}", date(task.due));
+    println!("Completed: {}", date(task.completed));
+    if task.tags.is_empty() {
+        println!("Tags:      -");
+    } else {
+        println!("Tags:      {}", format_tags(&task.tags));
+    }
+
+    if !task.description.is_empty() {
+        println!();
+        println!("{}", task.description);
+    }
+
+    if !task.checklist.is_empty() {
+        println!();
+        for item in &task.checklist {
+            println!("{} {}", checkbox(item.done), item.text);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_all(args: &[&str]) -> Result<Args, String> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        parse_args(&args)
+    }
+
+    fn parse(args: &[&str]) -> Result<Option<Command>, String> {
+        parse_all(args).map(|args| args.command)
+    }
+
+    #[test]
+    fn no_command_starts_the_ui() {
+        assert_eq!(parse(&[]), Ok(None));
+        assert_eq!(
+            parse_all(&["--db", "x.json"]),
+            Ok(Args {
+                db: Some(PathBuf::from("x.json")),
+                command: None
+            })
+        );
+    }
+
+    #[test]
+    fn db_without_a_path_is_an_error() {
+        assert!(parse(&["--db"]).is_err());
+        assert!(parse(&["--db="]).is_err());
+    }
+
+    #[test]
+    fn db_is_only_an_option_before_the_command() {
+        let args = parse_all(&["--db=x.json", "add", "task", "-d", "--db"]).unwrap();
+        assert_eq!(args.db, Some(PathBuf::from("x.json")));
+        assert_eq!(
+            args.command,
+            Some(Command::Add {
+                name: "task".to_string(),
+                priority: 0,
+                description: "--db".to_string(),
+            })
+        );
+
+        assert_eq!(parse_all(&["add", "task", "-d", "--db"]).unwrap().db, None);
+        assert!(parse(&["list", "--db", "x.json"]).is_err());
+    }
+
+    #[test]
+    fn add_with_options() {
+        assert_eq!(
+            parse(&["add", "write docs", "-p", "3", "-d", "the readme"]),
+            Ok(Some(Command::Add {
+                name: "write docs".to_string(),
+                priority: 3,
+                description: "the readme".to_string(),
+            }))
+        );
+        assert!(parse(&["add", "-p", "x", "name"]).is_err());
+        assert!(parse(&["add"]).is_err());
+    }
+
+    #[test]
+    fn list_filters() {
+        assert_eq!(
+            parse(&["list", "--done", "--json"]),
+            Ok(Some(Command::List {
+                tab: MenuItem::Done,
+                json: true
+            }))
+        );
+        assert!(parse(&["list", "--open"]).is_err());
+    }
+
+    #[test]
+    fn ids() {
+        assert_eq!(parse(&["rm", "12"]), Ok(Some(Command::Remove(12))));
+        assert!(parse(&["done"]).is_err());
+        assert!(parse(&["show", "abc"]).is_err());
+        assert!(parse(&["show", "1", "2"]).is_err());
+    }
+
+    #[test]
+    fn export_options() {
+        assert_eq!(
+            parse(&["export", "md", "--done", "-o", "out.md"]),
+            Ok(Some(Command::Export {
+                format: ExportFormat::Markdown,
+                tab: MenuItem::Done,
+                output: Some(PathBuf::from("out.md")),
+            }))
+        );
+        assert!(parse(&["export"]).is_err());
+        assert!(parse(&["export", "pdf"]).is_err());
+    }
+
+    #[test]
+    fn import_options() {
+        assert_eq!(
+            parse(&["import", "list.txt", "--dedupe", "-n"]),
+            Ok(Some(Command::Import {
+                path: PathBuf::from("list.txt"),
+                format: None,
+                dedupe: true,
+                dry_run: true,
+                yes: false,
+            }))
+        );
+        assert!(parse(&["import", "list.json"]).is_err());
+        assert!(parse(&["import", "list.json", "--format", "csv"]).is_ok());
+    }
+}
@@ -0,0 +1,113 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use crate::{schema::serialize_database, LocalError};
+
+/// Environment variable that overrides the database location
+const DB_ENV_VAR: &str = "TOODLES_DB";
+
+/// Environment variable that overrides the key binding file
+const KEYS_ENV_VAR: &str = "TOODLES_KEYS";
+
+/// Environment variable that overrides the theme file
+const THEMES_ENV_VAR: &str = "TOODLES_THEMES";
+
+static DB_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+/// Location of the database file, set once at startup by `init_db_path`
+pub fn db_path() -> &'static Path {
+    DB_PATH
+        .get()
+        .expect("Database path is set before anything touches the database")
+}
+
+/// Location of the next ID counter, kept next to the database so each database has its own
+pub fn next_id_path(db_path: &Path) -> PathBuf {
+    db_path.with_extension("next_id")
+}
+
+/// Decide where the database lives and create it when it does not exist yet.
+///
+/// The first match wins: the path given with `--db`, the `TOODLES_DB` environment variable,
+/// then `toodles/db.json` in the XDG data directory.
+pub fn init_db_path(db: Option<PathBuf>) -> Result<(), LocalError> {
+    let path = db
+        .or_else(|| env::var_os(DB_ENV_VAR).map(PathBuf::from))
+        .unwrap_or_else(default_db_path);
+
+    bootstrap_database(&path)?;
+
+    DB_PATH
+        .set(path)
+        .expect("Database path is only set once at startup");
+
+    Ok(())
+}
+
+/// `$XDG_DATA_HOME/toodles/db.json`, falling back to `~/.local/share/toodles/db.json`
+fn default_db_path() -> PathBuf {
+    let data_home = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        .unwrap_or_else(|| PathBuf::from("."));
+
+    data_home.join("toodles").join("db.json")
+}
+
+/// Location of the key binding file.
+///
+/// The `TOODLES_KEYS` environment variable wins, otherwise `toodles/keys.toml` in the XDG config
+/// directory, or `toodles/keys.json` when only that one exists.
+pub fn keymap_path() -> PathBuf {
+    if let Some(path) = env::var_os(KEYS_ENV_VAR) {
+        return PathBuf::from(path);
+    }
+
+    let toml = config_dir().join("keys.toml");
+    let json = toml.with_extension("json");
+
+    if !toml.exists() && json.exists() {
+        json
+    } else {
+        toml
+    }
+}
+
+/// Location of the file with user themes, `TOODLES_THEMES` or `toodles/themes.toml` in the XDG
+/// config directory
+pub fn themes_path() -> PathBuf {
+    env::var_os(THEMES_ENV_VAR)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| config_dir().join("themes.toml"))
+}
+
+/// `$XDG_CONFIG_HOME/toodles`, falling back to `~/.config/toodles`
+fn config_dir() -> PathBuf {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .unwrap_or_else(|| PathBuf::from("."));
+
+    config_home.join("toodles")
+}
+
+/// Create the database and its parent directories with an empty task list on first run
+fn bootstrap_database(path: &Path) -> Result<(), LocalError> {
+    if path.exists() {
+        return Ok(());
+    }
+
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent).map_err(|e| LocalError::from_write(parent, e))?;
+    }
+
+    fs::write(path, serialize_database(&[], &[])).map_err(|e| LocalError::from_write(path, e))
+}
@@ -0,0 +1,201 @@
+use std::cmp::Reverse;
+
+use chrono::{prelude::Local, DateTime, Duration};
+
+use crate::{
+    due::{due_state, DueState},
+    Task,
+};
+
+/// Days of completions shown in the sparkline on Home
+pub const COMPLETION_DAYS: usize = 28;
+
+/// How many of the latest completed tasks are listed on Home
+const RECENTLY_COMPLETED: usize = 5;
+
+/// Why a task is listed on Home
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EntryKind {
+    Overdue,
+    DueToday,
+    Completed,
+}
+
+/// A task listed on Home, selecting it jumps to the task
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HomeEntry {
+    pub kind: EntryKind,
+    pub id: usize,
+}
+
+/// Overdue tasks, then the ones due today, both by due date, then the latest completed ones
+pub fn home_entries(tasks: &[Task], now: DateTime<Local>) -> Vec<HomeEntry> {
+    let mut due: Vec<(EntryKind, &Task)> = tasks
+        .iter()
+        .filter(|task| !task.complete)
+        .filter_map(|task| match due_state(task.due?, now) {
+            DueState::Overdue => Some((EntryKind::Overdue, task)),
+            DueState::DueToday => Some((EntryKind::DueToday, task)),
+            DueState::Upcoming => None,
+        })
+        .collect();
+    due.sort_by_key(|(kind, task)| (*kind != EntryKind::Overdue, task.due));
+
+    let mut completed: Vec<&Task> = tasks
+        .iter()
+        .filter(|task| task.complete && task.completed.is_some())
+        .collect();
+    completed.sort_by_key(|task| Reverse(task.completed));
+
+    due.into_iter()
+        .chain(
+            completed
+                .into_iter()
+                .take(RECENTLY_COMPLETED)
+                .map(|task| (EntryKind::Completed, task)),
+        )
+        .map(|(kind, task)| HomeEntry { kind, id: task.id })
+        .collect()
+}
+
+/// Tasks completed on each of the last `days` days, oldest first and today last
+pub fn completions_per_day(tasks: &[Task], now: DateTime<Local>, days: usize) -> Vec<u64> {
+    let today = now.naive_local().date();
+    let mut counts = vec![0; days];
+
+    for completed in tasks.iter().filter_map(|task| task.completed) {
+        let days_ago = (today - completed.naive_local().date()).num_days();
+
+        if (0..days as i64).contains(&days_ago) {
+            counts[days - 1 - days_ago as usize] += 1;
+        }
+    }
+
+    counts
+}
+
+/// Open tasks per priority band, from high to none
+pub fn open_by_priority(tasks: &[Task]) -> [(&'static str, u64); 4] {
+    let mut bands = [("high", 0), ("medium", 0), ("low", 0), ("none", 0)];
+
+    for task in tasks.iter().filter(|task| !task.complete) {
+        let band = match task.priority {
+            0 => 3,
+            1..=3 => 2,
+            4..=6 => 1,
+            _ => 0,
+        };
+        bands[band].1 += 1;
+    }
+
+    bands
+}
+
+/// Tasks completed within the last `days` days, for the title of the sparkline
+pub fn completed_since(tasks: &[Task], now: DateTime<Local>, days: usize) -> usize {
+    let since = now - Duration::days(days as i64);
+
+    tasks
+        .iter()
+        .filter_map(|task| task.completed)
+        .filter(|completed| *completed > since)
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    /// A time in May 2024
+    fn may(day: u32, hour: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 5, day, hour, 0, 0).unwrap()
+    }
+
+    fn now() -> DateTime<Local> {
+        may(10, 12)
+    }
+
+    fn task(id: usize, due: Option<DateTime<Local>>) -> Task {
+        Task {
+            id,
+            due,
+            ..Task::default()
+        }
+    }
+
+    fn done(id: usize, completed: DateTime<Local>) -> Task {
+        Task {
+            id,
+            complete: true,
+            completed: Some(completed),
+            ..Task::default()
+        }
+    }
+
+    #[test]
+    fn overdue_first_then_due_today_then_completed() {
+        let tasks = vec![
+            task(1, Some(may(10, 18))),
+            task(2, Some(may(9, 9))),
+            task(3, Some(may(12, 9))),
+            task(4, None),
+            done(5, may(1, 9)),
+            done(6, may(8, 9)),
+        ];
+
+        assert_eq!(
+            home_entries(&tasks, now()),
+            vec![
+                HomeEntry {
+                    kind: EntryKind::Overdue,
+                    id: 2
+                },
+                HomeEntry {
+                    kind: EntryKind::DueToday,
+                    id: 1
+                },
+                HomeEntry {
+                    kind: EntryKind::Completed,
+                    id: 6
+                },
+                HomeEntry {
+                    kind: EntryKind::Completed,
+                    id: 5
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn completions_are_counted_per_day() {
+        let tasks = vec![
+            done(1, may(10, 8)),
+            done(2, may(10, 9)),
+            done(3, may(8, 9)),
+            // outside of the window
+            done(4, may(1, 9)),
+        ];
+
+        assert_eq!(completions_per_day(&tasks, now(), 5), vec![0, 0, 1, 0, 2]);
+        assert_eq!(completed_since(&tasks, now(), 5), 3);
+    }
+
+    #[test]
+    fn open_tasks_per_priority_band() {
+        let mut tasks: Vec<Task> = [0, 2, 5, 9, 9]
+            .iter()
+            .map(|priority| Task {
+                priority: *priority,
+                ..Task::default()
+            })
+            .collect();
+        tasks.push(done(7, now()));
+
+        assert_eq!(
+            open_by_priority(&tasks),
+            [("high", 2), ("medium", 1), ("low", 1), ("none", 1)]
+        );
+    }
+}
@@ -0,0 +1,179 @@
+use chrono::{
+    prelude::Local, DateTime, Datelike, Duration, NaiveDate, NaiveTime, TimeDelta, TimeZone,
+    Weekday,
+};
+
+/// How due dates are shown and how they are pre-filled in the edit form
+pub const DUE_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// How close a task is to its due date, used to colour the task list
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DueState {
+    Overdue,
+    DueToday,
+    Upcoming,
+}
+
+/// Where a due date stands relative to `now`
+pub fn due_state(due: DateTime<Local>, now: DateTime<Local>) -> DueState {
+    if due < now {
+        DueState::Overdue
+    } else if due.naive_local().date() == now.naive_local().date() {
+        DueState::DueToday
+    } else {
+        DueState::Upcoming
+    }
+}
+
+/// Parse a due date typed into the task form.
+///
+/// The date part can be `today`, `tomorrow`, a relative offset like `+3d` / `+2w`, a weekday
+/// (`fri`, `friday`, always the next one after today) or an ISO date `2024-05-01`. It can be
+/// followed by a time `14:30`; without one the task is due at the end of that day.
+/// An empty input means no due date.
+pub fn parse_due(input: &str, now: DateTime<Local>) -> Result<Option<DateTime<Local>>, String> {
+    let mut parts = input.split_whitespace();
+
+    let date_part = match parts.next() {
+        Some(part) => part.to_lowercase(),
+        None => return Ok(None),
+    };
+
+    let date = parse_date(&date_part, now.naive_local().date())
+        .ok_or_else(|| format!("Cannot understand due date '{}'", date_part))?;
+
+    let time = match parts.next() {
+        Some(part) => NaiveTime::parse_from_str(part, "%H:%M")
+            .map_err(|_| format!("Cannot understand due time '{}'", part))?,
+        None => NaiveTime::from_hms_opt(23, 59, 0).expect("23:59 is a valid time"),
+    };
+
+    if let Some(extra) = parts.next() {
+        return Err(format!("Unexpected '{}' after the due date", extra));
+    }
+
+    Local
+        .from_local_datetime(&date.and_time(time))
+        .earliest()
+        .map(Some)
+        .ok_or_else(|| format!("'{}' does not exist in the local time zone", input.trim()))
+}
+
+fn parse_date(input: &str, today: NaiveDate) -> Option<NaiveDate> {
+    match input {
+        "today" | "tod" => return Some(today),
+        "tomorrow" | "tom" => return today.succ_opt(),
+        _ => {}
+    }
+
+    if let Some(offset) = input.strip_prefix('+') {
+        return parse_offset(offset).and_then(|offset| today.checked_add_signed(offset));
+    }
+
+    if let Some(weekday) = parse_weekday(input) {
+        let days_ahead =
+            (weekday.num_days_from_monday() + 7 - today.weekday().num_days_from_monday() - 1) % 7
+                + 1;
+
+        return today.checked_add_signed(Duration::days(days_ahead as i64));
+    }
+
+    NaiveDate::parse_from_str(input, "%Y-%m-%d").ok()
+}
+
+/// `3d`, `2w` or a bare number of days, `None` when it does not fit into a `TimeDelta`
+fn parse_offset(input: &str) -> Option<TimeDelta> {
+    let (amount, unit) = match input.find(|c: char| !c.is_ascii_digit()) {
+        Some(index) => input.split_at(index),
+        None => (input, "d"),
+    };
+
+    let amount: i64 = amount.parse().ok()?;
+
+    match unit {
+        "d" => TimeDelta::try_days(amount),
+        "w" => TimeDelta::try_weeks(amount),
+        _ => None,
+    }
+}
+
+fn parse_weekday(input: &str) -> Option<Weekday> {
+    let weekday = match input {
+        "mon" | "monday" => Weekday::Mon,
+        "tue" | "tues" | "tuesday" => Weekday::Tue,
+        "wed" | "wednesday" => Weekday::Wed,
+        "thu" | "thur" | "thurs" | "thursday" => Weekday::Thu,
+        "fri" | "friday" => Weekday::Fri,
+        "sat" | "saturday" => Weekday::Sat,
+        "sun" | "sunday" => Weekday::Sun,
+        _ => return None,
+    };
+
+    Some(weekday)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Wednesday 2024-05-01 10:00
+    fn now() -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 5, 1, 10, 0, 0).unwrap()
+    }
+
+    fn due(input: &str) -> String {
+        parse_due(input, now())
+            .unwrap()
+            .unwrap()
+            .format(DUE_FORMAT)
+            .to_string()
+    }
+
+    #[test]
+    fn relative_words() {
+        assert_eq!(due("today"), "2024-05-01 23:59");
+        assert_eq!(due("Tomorrow 9:30"), "2024-05-02 09:30");
+    }
+
+    #[test]
+    fn offsets() {
+        assert_eq!(due("+3d"), "2024-05-04 23:59");
+        assert_eq!(due("+2w"), "2024-05-15 23:59");
+        assert_eq!(due("+1"), "2024-05-02 23:59");
+    }
+
+    #[test]
+    fn weekdays_are_always_in_the_future() {
+        assert_eq!(due("fri"), "2024-05-03 23:59");
+        assert_eq!(due("wednesday"), "2024-05-08 23:59");
+    }
+
+    #[test]
+    fn iso_dates_and_times() {
+        assert_eq!(due("2024-06-30 17:45"), "2024-06-30 17:45");
+    }
+
+    #[test]
+    fn empty_and_invalid_input() {
+        assert_eq!(parse_due("  ", now()), Ok(None));
+        assert!(parse_due("someday", now()).is_err());
+        assert!(parse_due("fri 25:00", now()).is_err());
+        assert!(parse_due("+3x", now()).is_err());
+    }
+
+    #[test]
+    fn huge_offsets_are_rejected() {
+        assert!(parse_due("+999999999999999d", now()).is_err());
+        assert!(parse_due("+999999999999999w", now()).is_err());
+        assert!(parse_due("+99999999999999999999d", now()).is_err());
+    }
+
+    #[test]
+    fn due_states() {
+        let now = now();
+
+        assert_eq!(due_state(now - Duration::hours(1), now), DueState::Overdue);
+        assert_eq!(due_state(now + Duration::hours(1), now), DueState::DueToday);
+        assert_eq!(due_state(now + Duration::days(2), now), DueState::Upcoming);
+    }
+}
@@ -0,0 +1,262 @@
+use std::{fs, path::Path};
+
+use strum_macros::{Display, EnumIter};
+
+use crate::{
+    checklist::{checkbox, format_checklist},
+    tags::format_tags,
+    LocalError, Task,
+};
+
+/// How todo.txt dates are written
+const TODO_TXT_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Formats tasks can be exported to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Display, EnumIter)]
+pub enum ExportFormat {
+    #[strum(serialize = "Markdown")]
+    Markdown,
+    #[strum(serialize = "CSV")]
+    Csv,
+    #[strum(serialize = "todo.txt")]
+    TodoTxt,
+}
+
+impl ExportFormat {
+    /// Name used on the command line
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "markdown" | "md" => Some(ExportFormat::Markdown),
+            "csv" => Some(ExportFormat::Csv),
+            "todotxt" | "todo.txt" | "txt" => Some(ExportFormat::TodoTxt),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => "md",
+            ExportFormat::Csv => "csv",
+            ExportFormat::TodoTxt => "txt",
+        }
+    }
+}
+
+/// Write the tasks in the given format, in the order they are passed in
+pub fn export(tasks: &[&Task], format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Markdown => to_markdown(tasks),
+        ExportFormat::Csv => to_csv(tasks),
+        ExportFormat::TodoTxt => to_todo_txt(tasks),
+    }
+}
+
+/// Export the tasks into a file, replacing it when it exists
+pub fn export_to_file(
+    tasks: &[&Task],
+    format: ExportFormat,
+    path: &Path,
+) -> Result<(), LocalError> {
+    fs::write(path, export(tasks, format)).map_err(|e| LocalError::from_write(path, e))
+}
+
+/// A checklist per task, checklist items are nested below their task
+fn to_markdown(tasks: &[&Task]) -> String {
+    let mut output = String::new();
+
+    for task in tasks {
+        output.push_str(&format!("- {} {}", checkbox(task.complete), task.name));
+
+        if !task.tags.is_empty() {
+            output.push_str(&format!(" {}", format_tags(&task.tags)));
+        }
+        output.push('\n');
+
+        for item in &task.checklist {
+            output.push_str(&format!("  - {} {}\n", checkbox(item.done), item.text));
+        }
+    }
+
+    output
+}
+
+pub const CSV_HEADER: [&str; 10] = [
+    "id",
+    "name",
+    "description",
+    "complete",
+    "priority",
+    "created",
+    "completed",
+    "due",
+    "tags",
+    "checklist",
+];
+
+/// Every field of every task, dates in RFC 3339 so they read back without loss
+fn to_csv(tasks: &[&Task]) -> String {
+    let mut output = csv_row(CSV_HEADER.iter().map(|field| field.to_string()));
+
+    for task in tasks {
+        output.push_str(&csv_row(
+            [
+                task.id.to_string(),
+                task.name.clone(),
+                task.description.clone(),
+                task.complete.to_string(),
+                task.priority.to_string(),
+                task.created.to_rfc3339(),
+                task.completed
+                    .map(|date| date.to_rfc3339())
+                    .unwrap_or_default(),
+                task.due.map(|date| date.to_rfc3339()).unwrap_or_default(),
+                format_tags(&task.tags),
+                format_checklist(&task.checklist),
+            ]
+            .into_iter(),
+        ));
+    }
+
+    output
+}
+
+/// One line of CSV, fields with separators, quotes or line breaks are quoted
+fn csv_row(fields: impl Iterator<Item = String>) -> String {
+    let fields: Vec<String> = fields
+        .map(|field| {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field
+            }
+        })
+        .collect();
+
+    format!("{}\n", fields.join(","))
+}
+
+/// Priority letter for todo.txt, `A` is the most important. Priority 9 and up is `A`,
+/// 1 is `I` and 0 has no priority.
+pub fn todo_txt_priority(priority: usize) -> Option<char> {
+    match priority {
+        0 => None,
+        priority => Some((b'A' + 9 - priority.min(9) as u8) as char),
+    }
+}
+
+/// One task per line following the todo.txt format: `x` and the completion date for done tasks,
+/// `(A)` priority for open ones, the creation date, `+project` and `@context` tags and a
+/// `due:` date
+fn to_todo_txt(tasks: &[&Task]) -> String {
+    let mut output = String::new();
+
+    for task in tasks {
+        let mut parts = Vec::new();
+        let priority = todo_txt_priority(task.priority);
+
+        if task.complete {
+            parts.push("x".to_string());
+            if let Some(completed) = task.completed {
+                parts.push(completed.format(TODO_TXT_DATE_FORMAT).to_string());
+            }
+        } else if let Some(priority) = priority {
+            parts.push(format!("({})", priority));
+        }
+
+        parts.push(task.created.format(TODO_TXT_DATE_FORMAT).to_string());
+        // line breaks would start a new task
+        parts.push(task.name.replace(['\n', '\r'], " "));
+
+        for tag in &task.tags {
+            match tag.strip_prefix('#') {
+                Some(project) => parts.push(format!("+{}", project)),
+                None => parts.push(tag.clone()),
+            }
+        }
+
+        if let Some(due) = task.due {
+            parts.push(format!("due:{}", due.format(TODO_TXT_DATE_FORMAT)));
+        }
+
+        // done tasks lose their priority marker, the standard keeps it as a tag instead
+        if let (true, Some(priority)) = (task.complete, priority) {
+            parts.push(format!("pri:{}", priority));
+        }
+
+        output.push_str(&parts.join(" "));
+        output.push('\n');
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{prelude::Local, TimeZone};
+
+    use super::*;
+    use crate::ChecklistItem;
+
+    fn task() -> Task {
+        Task {
+            id: 4,
+            name: "write docs".to_string(),
+            description: "the \"readme\", mostly".to_string(),
+            priority: 7,
+            created: Local.with_ymd_and_hms(2024, 5, 1, 10, 0, 0).unwrap(),
+            due: Some(Local.with_ymd_and_hms(2024, 5, 3, 23, 59, 0).unwrap()),
+            tags: vec!["#docs".to_string(), "@work".to_string()],
+            checklist: vec![ChecklistItem {
+                text: "intro".to_string(),
+                done: true,
+            }],
+            ..Task::default()
+        }
+    }
+
+    fn done(mut task: Task) -> Task {
+        task.complete = true;
+        task.completed = Some(Local.with_ymd_and_hms(2024, 5, 2, 9, 0, 0).unwrap());
+        task
+    }
+
+    #[test]
+    fn markdown_checklist() {
+        let done = done(task());
+
+        assert_eq!(
+            export(&[&task(), &done], ExportFormat::Markdown),
+            "- [ ] write docs #docs @work\n  - [x] intro\n\
+             - [x] write docs #docs @work\n  - [x] intro\n"
+        );
+    }
+
+    #[test]
+    fn csv_quotes_special_fields() {
+        let output = export(&[&task()], ExportFormat::Csv);
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(lines[0], CSV_HEADER.join(","));
+        assert!(lines[1].starts_with("4,write docs,\"the \"\"readme\"\", mostly\",false,7,"));
+        assert!(lines[1].ends_with(",#docs @work,[x] intro"));
+    }
+
+    #[test]
+    fn todo_txt_open_and_done() {
+        let done = done(task());
+
+        assert_eq!(
+            export(&[&task(), &done], ExportFormat::TodoTxt),
+            "(C) 2024-05-01 write docs +docs @work due:2024-05-03\n\
+             x 2024-05-02 2024-05-01 write docs +docs @work due:2024-05-03 pri:C\n"
+        );
+    }
+
+    #[test]
+    fn priority_letters() {
+        assert_eq!(todo_txt_priority(0), None);
+        assert_eq!(todo_txt_priority(1), Some('I'));
+        assert_eq!(todo_txt_priority(9), Some('A'));
+        assert_eq!(todo_txt_priority(42), Some('A'));
+    }
+}
@@ -0,0 +1,448 @@
+use chrono::prelude::Local;
+use crossterm::event::{KeyCode, KeyEvent};
+
+use crate::{
+    checklist::{format_checklist, parse_checklist},
+    due::{parse_due, DUE_FORMAT},
+    keymap::Action,
+    tags::{format_tags, parse_tags},
+    Note, NoteKind, Task,
+};
+
+/// Which field of the task form currently has focus
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FormField {
+    Name,
+    Description,
+    Checklist,
+    Priority,
+    Due,
+    Tags,
+}
+
+impl FormField {
+    fn next(&mut self) {
+        match self {
+            FormField::Name => *self = FormField::Description,
+            FormField::Description => *self = FormField::Checklist,
+            FormField::Checklist => *self = FormField::Priority,
+            FormField::Priority => *self = FormField::Due,
+            FormField::Due => *self = FormField::Tags,
+            FormField::Tags => *self = FormField::Name,
+        }
+    }
+
+    fn previous(&mut self) {
+        match self {
+            FormField::Name => *self = FormField::Tags,
+            FormField::Description => *self = FormField::Name,
+            FormField::Checklist => *self = FormField::Description,
+            FormField::Priority => *self = FormField::Checklist,
+            FormField::Due => *self = FormField::Priority,
+            FormField::Tags => *self = FormField::Due,
+        }
+    }
+}
+
+/// What the caller should do after a key was fed to the form
+pub enum FormAction {
+    None,
+    Submit,
+    Cancel,
+}
+
+/// A single editable line (or block) of text with a cursor.
+///
+/// The cursor is stored as a char index so multi byte input does not split characters.
+#[derive(Default, Clone)]
+pub struct TextField {
+    pub value: String,
+    pub cursor: usize,
+}
+
+impl TextField {
+    pub fn new(value: &str) -> Self {
+        Self {
+            value: value.to_string(),
+            cursor: value.chars().count(),
+        }
+    }
+
+    fn byte_index(&self) -> usize {
+        self.value
+            .char_indices()
+            .nth(self.cursor)
+            .map(|(i, _)| i)
+            .unwrap_or(self.value.len())
+    }
+
+    fn insert(&mut self, c: char) {
+        let index = self.byte_index();
+        self.value.insert(index, c);
+        self.cursor += 1;
+    }
+
+    fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            let index = self.byte_index();
+            self.value.remove(index);
+        }
+    }
+
+    fn delete(&mut self) {
+        if self.cursor < self.value.chars().count() {
+            let index = self.byte_index();
+            self.value.remove(index);
+        }
+    }
+
+    fn left(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    fn right(&mut self) {
+        self.cursor = (self.cursor + 1).min(self.value.chars().count());
+    }
+
+    /// Line and column of the cursor, used to place the terminal cursor when rendering
+    pub fn cursor_position(&self) -> (u16, u16) {
+        let before: String = self.value.chars().take(self.cursor).collect();
+        let line = before.matches('\n').count();
+        let column = before.rsplit('\n').next().unwrap_or("").chars().count();
+
+        (column as u16, line as u16)
+    }
+}
+
+/// State of the add / edit task popup
+pub struct TaskForm {
+    pub name: TextField,
+    pub description: TextField,
+    /// One checklist item per line, see `checklist::parse_checklist`
+    pub checklist: TextField,
+    pub priority: TextField,
+    /// Due date as typed, see `due::parse_due` for what is understood
+    pub due: TextField,
+    /// Tags as typed, see `tags::parse_tags`
+    pub tags: TextField,
+    pub focus: FormField,
+    /// ID of the task being edited, None when adding a new one
+    pub editing: Option<usize>,
+    /// Why the last attempt to save was refused
+    pub error: Option<String>,
+}
+
+impl Default for TaskForm {
+    fn default() -> Self {
+        Self {
+            name: TextField::default(),
+            description: TextField::default(),
+            checklist: TextField::default(),
+            priority: TextField::new("0"),
+            due: TextField::default(),
+            tags: TextField::default(),
+            focus: FormField::Name,
+            editing: None,
+            error: None,
+        }
+    }
+}
+
+impl TaskForm {
+    /// Create a form pre-filled with an existing task for editing
+    pub fn from_task(task: &Task) -> Self {
+        Self {
+            name: TextField::new(&task.name),
+            description: TextField::new(&task.description),
+            checklist: TextField::new(&format_checklist(&task.checklist)),
+            priority: TextField::new(&task.priority.to_string()),
+            due: TextField::new(
+                &task
+                    .due
+                    .map(|due| due.format(DUE_FORMAT).to_string())
+                    .unwrap_or_default(),
+            ),
+            tags: TextField::new(&format_tags(&task.tags)),
+            focus: FormField::Name,
+            editing: Some(task.id),
+            error: None,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = TaskForm::default();
+    }
+
+    fn focused_field(&mut self) -> &mut TextField {
+        match self.focus {
+            FormField::Name => &mut self.name,
+            FormField::Description => &mut self.description,
+            FormField::Checklist => &mut self.checklist,
+            FormField::Priority => &mut self.priority,
+            FormField::Due => &mut self.due,
+            FormField::Tags => &mut self.tags,
+        }
+    }
+
+    fn focus_is_multiline(&self) -> bool {
+        matches!(self.focus, FormField::Description | FormField::Checklist)
+    }
+
+    /// Check the fields before saving, the reason for a refusal is kept in `error`
+    pub fn validate(&mut self) -> bool {
+        self.error = if self.name.value.trim().is_empty() {
+            Some("A task needs a name".to_string())
+        } else {
+            parse_due(&self.due.value, Local::now()).err()
+        };
+
+        self.error.is_none()
+    }
+
+    /// Feed a key press to the form and report whether it should be submitted or closed.
+    /// `action` is what the key is bound to in the form layer, other keys edit the focused field.
+    pub fn handle_key(&mut self, key: KeyEvent, action: Option<Action>) -> FormAction {
+        match action {
+            Some(Action::Cancel) => return FormAction::Cancel,
+            Some(Action::Save) => return FormAction::Submit,
+            Some(Action::NextField) => self.focus.next(),
+            Some(Action::PrevField) => self.focus.previous(),
+            // only the description and checklist take more than one line
+            Some(Action::NewLine) if self.focus_is_multiline() => self.focused_field().insert('\n'),
+            Some(_) => {}
+            None => match key.code {
+                // priority only takes digits
+                KeyCode::Char(c) if self.focus != FormField::Priority || c.is_ascii_digit() => {
+                    self.focused_field().insert(c)
+                }
+                KeyCode::Backspace => self.focused_field().backspace(),
+                KeyCode::Delete => self.focused_field().delete(),
+                KeyCode::Left => self.focused_field().left(),
+                KeyCode::Right => self.focused_field().right(),
+                KeyCode::Home => self.focused_field().cursor = 0,
+                KeyCode::End => {
+                    let field = self.focused_field();
+                    field.cursor = field.value.chars().count();
+                }
+                _ => {}
+            },
+        }
+
+        FormAction::None
+    }
+
+    /// Build a task out of the form contents, returns None when the form does not validate.
+    /// The ID is left at 0 and assigned when the task is added to the list.
+    pub fn to_task(&self) -> Option<Task> {
+        let name = self.name.value.trim();
+        let due = parse_due(&self.due.value, Local::now()).ok()?;
+
+        if name.is_empty() {
+            return None;
+        }
+
+        Some(Task {
+            id: 0,
+            name: name.to_string(),
+            description: self.description.value.clone(),
+            complete: false,
+            priority: self.priority.value.parse().unwrap_or(0),
+            created: Local::now(),
+            completed: None,
+            due,
+            tags: parse_tags(&self.tags.value),
+            checklist: parse_checklist(&self.checklist.value),
+        })
+    }
+
+    /// Write the edited fields back onto an existing task.
+    /// Returns false when nothing changed or the form does not validate.
+    pub fn apply_to(&self, task: &mut Task) -> bool {
+        let name = self.name.value.trim();
+        let due = match parse_due(&self.due.value, Local::now()) {
+            Ok(due) => due,
+            Err(_) => return false,
+        };
+        let priority = self.priority.value.parse().unwrap_or(0);
+        let tags = parse_tags(&self.tags.value);
+        let checklist = parse_checklist(&self.checklist.value);
+
+        if name.is_empty()
+            || (task.name == name
+                && task.description == self.description.value
+                && task.priority == priority
+                && task.due == due
+                && task.tags == tags
+                && task.checklist == checklist)
+        {
+            return false;
+        }
+
+        task.name = name.to_string();
+        task.description = self.description.value.clone();
+        task.priority = priority;
+        task.due = due;
+        task.tags = tags;
+        task.checklist = checklist;
+
+        true
+    }
+}
+
+/// Which field of the note form currently has focus
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NoteField {
+    Title,
+    Kind,
+    Body,
+}
+
+impl NoteField {
+    fn next(&mut self) {
+        match self {
+            NoteField::Title => *self = NoteField::Kind,
+            NoteField::Kind => *self = NoteField::Body,
+            NoteField::Body => *self = NoteField::Title,
+        }
+    }
+
+    fn previous(&mut self) {
+        match self {
+            NoteField::Title => *self = NoteField::Body,
+            NoteField::Kind => *self = NoteField::Title,
+            NoteField::Body => *self = NoteField::Kind,
+        }
+    }
+}
+
+/// State of the add / edit note popup
+pub struct NoteForm {
+    pub title: TextField,
+    pub body: TextField,
+    pub kind: NoteKind,
+    pub focus: NoteField,
+    /// ID of the note being edited, None when adding a new one
+    pub editing: Option<usize>,
+    /// Why the last attempt to save was refused
+    pub error: Option<String>,
+}
+
+impl Default for NoteForm {
+    fn default() -> Self {
+        Self {
+            title: TextField::default(),
+            body: TextField::default(),
+            kind: NoteKind::Short,
+            focus: NoteField::Title,
+            editing: None,
+            error: None,
+        }
+    }
+}
+
+impl NoteForm {
+    /// Create a form pre-filled with an existing note for editing
+    pub fn from_note(note: &Note) -> Self {
+        Self {
+            title: TextField::new(&note.title),
+            body: TextField::new(&note.body),
+            kind: note.kind,
+            focus: NoteField::Title,
+            editing: Some(note.id),
+            error: None,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = NoteForm::default();
+    }
+
+    /// Check the fields before saving, the reason for a refusal is kept in `error`
+    pub fn validate(&mut self) -> bool {
+        self.error = if self.title.value.trim().is_empty() {
+            Some("A note needs a title".to_string())
+        } else {
+            None
+        };
+
+        self.error.is_none()
+    }
+
+    /// Feed a key press to the form and report whether it should be submitted or closed.
+    /// `action` is what the key is bound to in the form layer, or in the note kind layer while the
+    /// kind has focus. Other keys edit the focused field.
+    pub fn handle_key(&mut self, key: KeyEvent, action: Option<Action>) -> FormAction {
+        match action {
+            Some(Action::Cancel) => return FormAction::Cancel,
+            Some(Action::Save) => return FormAction::Submit,
+            Some(Action::NextField) => self.focus.next(),
+            Some(Action::PrevField) => self.focus.previous(),
+            // the body takes newlines the same way as the task description
+            Some(Action::NewLine) if self.focus == NoteField::Body => self.body.insert('\n'),
+            Some(Action::ToggleKind) if self.focus == NoteField::Kind => self.kind.toggle(),
+            Some(_) => {}
+            None => {
+                let field = match self.focus {
+                    NoteField::Title => &mut self.title,
+                    NoteField::Body => &mut self.body,
+                    // the kind is only changed by its toggle, nothing is typed into it
+                    NoteField::Kind => return FormAction::None,
+                };
+
+                match key.code {
+                    KeyCode::Char(c) => field.insert(c),
+                    KeyCode::Backspace => field.backspace(),
+                    KeyCode::Delete => field.delete(),
+                    KeyCode::Left => field.left(),
+                    KeyCode::Right => field.right(),
+                    KeyCode::Home => field.cursor = 0,
+                    KeyCode::End => field.cursor = field.value.chars().count(),
+                    _ => {}
+                }
+            }
+        }
+
+        FormAction::None
+    }
+
+    /// Build a note out of the form contents, returns None when the title is empty.
+    /// The ID is left at 0 and assigned when the note is added.
+    pub fn to_note(&self) -> Option<Note> {
+        let title = self.title.value.trim();
+
+        if title.is_empty() {
+            return None;
+        }
+
+        let now = Local::now();
+
+        Some(Note {
+            id: 0,
+            title: title.to_string(),
+            body: self.body.value.clone(),
+            kind: self.kind,
+            created: now,
+            modified: now,
+        })
+    }
+
+    /// Write the edited fields back onto an existing note and bump its modified time.
+    /// Returns false when nothing changed or the form does not validate.
+    pub fn apply_to(&self, note: &mut Note) -> bool {
+        let title = self.title.value.trim();
+
+        if title.is_empty()
+            || (note.title == title && note.body == self.body.value && note.kind == self.kind)
+        {
+            return false;
+        }
+
+        note.title = title.to_string();
+        note.body = self.body.value.clone();
+        note.kind = self.kind;
+        note.modified = Local::now();
+
+        true
+    }
+}
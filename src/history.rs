@@ -0,0 +1,134 @@
+use crate::{Note, Task};
+
+/// How many changes can be undone, older ones are forgotten. Every entry holds a full copy of
+/// the database, so the history is capped to keep a long session from growing without bound.
+const MAX_DEPTH: usize = 100;
+
+/// Contents of the database at one point in time
+#[derive(Clone, Default)]
+pub struct Snapshot {
+    pub tasks: Vec<Task>,
+    pub notes: Vec<Note>,
+}
+
+struct Entry {
+    /// What the change did, e.g. "delete 'buy milk'"
+    description: String,
+    snapshot: Snapshot,
+}
+
+/// Undo and redo stacks for the session.
+///
+/// Every change records the state from before it. Undoing swaps the current state for the last
+/// recorded one and keeps the current state around for redo. Making a new change drops the redo
+/// stack as it no longer follows on from the current state.
+#[derive(Default)]
+pub struct History {
+    undo: Vec<Entry>,
+    redo: Vec<Entry>,
+}
+
+impl History {
+    pub fn record(&mut self, description: String, before: Snapshot) {
+        self.undo.push(Entry {
+            description,
+            snapshot: before,
+        });
+        self.redo.clear();
+
+        if self.undo.len() > MAX_DEPTH {
+            self.undo.remove(0);
+        }
+    }
+
+    /// Step back once, returns what was undone and the state to restore
+    pub fn undo(&mut self, current: Snapshot) -> Option<(String, Snapshot)> {
+        step(&mut self.undo, &mut self.redo, current)
+    }
+
+    /// Step forward again after an undo, returns what was redone and the state to restore
+    pub fn redo(&mut self, current: Snapshot) -> Option<(String, Snapshot)> {
+        step(&mut self.redo, &mut self.undo, current)
+    }
+
+    /// Every recorded state, for bringing them in line with changes made outside the session
+    pub fn snapshots_mut(&mut self) -> impl Iterator<Item = &mut Snapshot> {
+        self.undo
+            .iter_mut()
+            .chain(self.redo.iter_mut())
+            .map(|entry| &mut entry.snapshot)
+    }
+}
+
+/// Take the newest entry from `from` and remember `current` in `to` so the step can be reversed
+fn step(
+    from: &mut Vec<Entry>,
+    to: &mut Vec<Entry>,
+    current: Snapshot,
+) -> Option<(String, Snapshot)> {
+    let entry = from.pop()?;
+
+    to.push(Entry {
+        description: entry.description.clone(),
+        snapshot: current,
+    });
+
+    Some((entry.description, entry.snapshot))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(amount_of_tasks: usize) -> Snapshot {
+        Snapshot {
+            tasks: vec![Task::default(); amount_of_tasks],
+            notes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn undo_and_redo_swap_states() {
+        let mut history = History::default();
+        history.record("add 'a'".to_string(), snapshot(0));
+
+        let (description, before) = history.undo(snapshot(1)).unwrap();
+        assert_eq!(description, "add 'a'");
+        assert_eq!(before.tasks.len(), 0);
+
+        let (description, after) = history.redo(snapshot(0)).unwrap();
+        assert_eq!(description, "add 'a'");
+        assert_eq!(after.tasks.len(), 1);
+    }
+
+    #[test]
+    fn new_change_drops_redo() {
+        let mut history = History::default();
+        history.record("add 'a'".to_string(), snapshot(0));
+        history.undo(snapshot(1));
+        history.record("add 'b'".to_string(), snapshot(0));
+
+        assert!(history.redo(snapshot(1)).is_none());
+        assert!(history.undo(snapshot(1)).is_some());
+        assert!(history.undo(snapshot(0)).is_none());
+    }
+
+    #[test]
+    fn only_the_latest_changes_can_be_undone() {
+        let mut history = History::default();
+        for amount_of_tasks in 0..MAX_DEPTH + 10 {
+            history.record(
+                format!("add {}", amount_of_tasks),
+                snapshot(amount_of_tasks),
+            );
+        }
+
+        let mut undone = Vec::new();
+        while let Some((_, before)) = history.undo(snapshot(0)) {
+            undone.push(before.tasks.len());
+        }
+
+        assert_eq!(undone.len(), MAX_DEPTH);
+        assert_eq!(undone.last(), Some(&10));
+    }
+}
@@ -0,0 +1,496 @@
+use std::path::Path;
+
+use chrono::{prelude::Local, DateTime, NaiveDate, TimeZone};
+
+use crate::{checklist::parse_checklist, due::parse_due, tags::parse_tags, ChecklistItem, Task};
+
+/// Formats tasks can be imported from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImportFormat {
+    Markdown,
+    Csv,
+    TodoTxt,
+}
+
+impl ImportFormat {
+    /// Name used on the command line
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "markdown" | "md" => Some(ImportFormat::Markdown),
+            "csv" => Some(ImportFormat::Csv),
+            "todotxt" | "todo.txt" | "txt" => Some(ImportFormat::TodoTxt),
+            _ => None,
+        }
+    }
+
+    /// Guess the format from the file extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "md" | "markdown" => Some(ImportFormat::Markdown),
+            "csv" => Some(ImportFormat::Csv),
+            "txt" => Some(ImportFormat::TodoTxt),
+            _ => None,
+        }
+    }
+}
+
+/// Tasks read from an import file and the lines that could not be used
+pub struct Parsed {
+    /// IDs are left at 0, they are handed out when the tasks are added to the store
+    pub tasks: Vec<Task>,
+    /// Why some lines or rows were skipped, with their line number
+    pub problems: Vec<String>,
+}
+
+pub fn parse(content: &str, format: ImportFormat) -> Parsed {
+    match format {
+        ImportFormat::Markdown => parse_markdown(content),
+        ImportFormat::Csv => parse_csv(content),
+        ImportFormat::TodoTxt => parse_todo_txt(content),
+    }
+}
+
+/// Drop tasks whose name is already taken, either by an existing task or by an earlier task of
+/// the import. Names are compared ignoring case and surrounding whitespace.
+/// Returns the names of the dropped tasks.
+pub fn dedupe_by_name<'a>(
+    tasks: &mut Vec<Task>,
+    existing: impl Iterator<Item = &'a Task>,
+) -> Vec<String> {
+    let key = |name: &str| name.trim().to_lowercase();
+    let mut seen: Vec<String> = existing.map(|task| key(&task.name)).collect();
+    let mut dropped = Vec::new();
+
+    tasks.retain(|task| {
+        let name = key(&task.name);
+
+        if seen.contains(&name) {
+            dropped.push(task.name.clone());
+            false
+        } else {
+            seen.push(name);
+            true
+        }
+    });
+
+    dropped
+}
+
+fn new_task(name: &str) -> Task {
+    Task {
+        name: name.trim().to_string(),
+        created: Local::now(),
+        ..Task::default()
+    }
+}
+
+/// `- [ ] name` and `- [x] name` lines become tasks, trailing `#project` / `@context` words
+/// their tags. Indented checklist lines below a task become its checklist, everything else is
+/// ignored.
+fn parse_markdown(content: &str) -> Parsed {
+    let mut tasks: Vec<Task> = Vec::new();
+
+    for line in content.lines() {
+        let indented = line.starts_with([' ', '\t']);
+        let (done, text) = match markdown_item(line.trim()) {
+            Some(item) => item,
+            None => continue,
+        };
+
+        match tasks.last_mut() {
+            Some(task) if indented => task.checklist.push(ChecklistItem {
+                text: text.to_string(),
+                done,
+            }),
+            _ => {
+                let (name, tags) = split_trailing_tags(text);
+                let mut task = new_task(name);
+                task.tags = tags;
+
+                if done {
+                    task.complete = true;
+                    task.completed = Some(Local::now());
+                }
+
+                tasks.push(task);
+            }
+        }
+    }
+
+    Parsed {
+        tasks,
+        problems: Vec::new(),
+    }
+}
+
+/// Done state and text of a `- [ ] text` list item
+fn markdown_item(line: &str) -> Option<(bool, &str)> {
+    let rest = line
+        .strip_prefix("- ")
+        .or_else(|| line.strip_prefix("* "))?
+        .trim_start();
+
+    let (done, text) = if let Some(text) = rest.strip_prefix("[ ]") {
+        (false, text)
+    } else if let Some(text) = rest
+        .strip_prefix("[x]")
+        .or_else(|| rest.strip_prefix("[X]"))
+    {
+        (true, text)
+    } else {
+        return None;
+    };
+
+    let text = text.trim();
+
+    (!text.is_empty()).then_some((done, text))
+}
+
+/// Split `name #a @b` into the name and its tags
+fn split_trailing_tags(text: &str) -> (&str, Vec<String>) {
+    let mut name = text;
+
+    while let Some((rest, word)) = name.trim_end().rsplit_once(' ') {
+        if word.len() > 1 && word.starts_with(['#', '@']) {
+            name = rest;
+        } else {
+            break;
+        }
+    }
+
+    (name.trim(), parse_tags(&text[name.len()..]))
+}
+
+/// Priority from a todo.txt letter, the reverse of `export::todo_txt_priority`
+fn priority_from_letter(letter: char) -> Option<usize> {
+    letter
+        .is_ascii_uppercase()
+        .then(|| 9usize.saturating_sub((letter as u8 - b'A') as usize).max(1))
+}
+
+/// Start of the given day, todo.txt dates have no time
+fn todo_txt_date(word: &str) -> Option<DateTime<Local>> {
+    let date = NaiveDate::parse_from_str(word, "%Y-%m-%d").ok()?;
+
+    Local
+        .from_local_datetime(&date.and_hms_opt(0, 0, 0)?)
+        .earliest()
+}
+
+/// One task per line: `x` with completion date, `(A)` priority, creation date, then the text
+/// with `+project`, `@context`, `due:` and `pri:` words
+fn parse_todo_txt(content: &str) -> Parsed {
+    let mut tasks = Vec::new();
+    let mut problems = Vec::new();
+
+    for (number, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let mut words = line.split_whitespace().peekable();
+        let mut task = new_task("");
+
+        if words.peek() == Some(&"x") {
+            words.next();
+            task.complete = true;
+            task.completed = words.peek().and_then(|word| todo_txt_date(word));
+            if task.completed.is_some() {
+                words.next();
+            } else {
+                task.completed = Some(Local::now());
+            }
+        }
+
+        if let Some(priority) = words.peek().and_then(|word| {
+            let letter = word.strip_prefix('(')?.strip_suffix(')')?;
+            let mut letters = letter.chars();
+            match (letters.next(), letters.next()) {
+                (Some(letter), None) => priority_from_letter(letter),
+                _ => None,
+            }
+        }) {
+            task.priority = priority;
+            words.next();
+        }
+
+        if let Some(created) = words.peek().and_then(|word| todo_txt_date(word)) {
+            task.created = created;
+            words.next();
+        }
+
+        let mut name = Vec::new();
+
+        for word in words {
+            if let Some(project) = word.strip_prefix('+').filter(|p| !p.is_empty()) {
+                task.tags.extend(parse_tags(&format!("#{}", project)));
+            } else if word.len() > 1 && word.starts_with('@') {
+                task.tags.extend(parse_tags(word));
+            } else if let Some(due) = word.strip_prefix("due:") {
+                match parse_due(due, Local::now()) {
+                    Ok(due) => task.due = due,
+                    Err(e) => problems.push(format!("line {}: {}", number + 1, e)),
+                }
+            } else if let Some(letter) = word.strip_prefix("pri:") {
+                task.priority = letter
+                    .chars()
+                    .next()
+                    .and_then(priority_from_letter)
+                    .unwrap_or(task.priority);
+            } else {
+                name.push(word);
+            }
+        }
+
+        if name.is_empty() {
+            problems.push(format!("line {}: no task text", number + 1));
+            continue;
+        }
+
+        task.name = name.join(" ");
+        tasks.push(task);
+    }
+
+    Parsed { tasks, problems }
+}
+
+/// CSV with a header row. Columns are matched by name (`name`, `description`, `complete`,
+/// `priority`, `created`, `completed`, `due`, `tags`, `checklist`), only `name` is required and
+/// unknown columns are ignored, so the output of the CSV export reads back.
+fn parse_csv(content: &str) -> Parsed {
+    let mut rows = csv_records(content).into_iter();
+    let mut tasks = Vec::new();
+    let mut problems = Vec::new();
+
+    let header: Vec<String> = match rows.next() {
+        Some((_, header)) => header.iter().map(|h| h.trim().to_lowercase()).collect(),
+        None => {
+            return Parsed {
+                tasks,
+                problems: vec!["the file is empty".to_string()],
+            }
+        }
+    };
+
+    if !header.iter().any(|column| column == "name") {
+        problems.push("the header has no 'name' column".to_string());
+        return Parsed { tasks, problems };
+    }
+
+    for (line, row) in rows {
+        let field = |column: &str| {
+            header
+                .iter()
+                .position(|h| h == column)
+                .and_then(|index| row.get(index))
+                .map(|value| value.trim())
+                .filter(|value| !value.is_empty())
+        };
+
+        match csv_task(field) {
+            Ok(task) => tasks.push(task),
+            Err(e) => problems.push(format!("line {}: {}", line, e)),
+        }
+    }
+
+    Parsed { tasks, problems }
+}
+
+fn csv_task<'a>(field: impl Fn(&str) -> Option<&'a str>) -> Result<Task, String> {
+    let mut task = new_task(field("name").ok_or("the name is empty")?);
+
+    if let Some(description) = field("description") {
+        task.description = description.to_string();
+    }
+
+    if let Some(priority) = field("priority") {
+        task.priority = priority
+            .parse()
+            .map_err(|_| format!("priority '{}' is not a number", priority))?;
+    }
+
+    if let Some(complete) = field("complete") {
+        task.complete = match complete.to_lowercase().as_str() {
+            "true" | "yes" | "y" | "x" | "1" => true,
+            "false" | "no" | "n" | "0" => false,
+            other => return Err(format!("cannot tell whether '{}' means done", other)),
+        };
+    }
+
+    let date = |column: &str| -> Result<Option<DateTime<Local>>, String> {
+        match field(column) {
+            Some(value) => csv_date(value).map(Some),
+            None => Ok(None),
+        }
+    };
+
+    task.created = date("created")?.unwrap_or(task.created);
+    task.completed = date("completed")?;
+    if task.complete && task.completed.is_none() {
+        task.completed = Some(Local::now());
+    }
+
+    if let Some(due) = field("due") {
+        task.due = match DateTime::parse_from_rfc3339(due) {
+            Ok(due) => Some(due.with_timezone(&Local)),
+            Err(_) => parse_due(due, Local::now())?,
+        };
+    }
+
+    task.tags = field("tags").map(parse_tags).unwrap_or_default();
+    task.checklist = field("checklist").map(parse_checklist).unwrap_or_default();
+
+    Ok(task)
+}
+
+/// RFC 3339 as written by the export, or a plain `YYYY-MM-DD`
+fn csv_date(value: &str) -> Result<DateTime<Local>, String> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|date| date.with_timezone(&Local))
+        .ok()
+        .or_else(|| todo_txt_date(value))
+        .ok_or_else(|| format!("cannot understand date '{}'", value))
+}
+
+/// Split CSV into records of fields with the line each record starts on. Quoted fields can
+/// contain separators, doubled quotes and line breaks.
+fn csv_records(content: &str) -> Vec<(usize, Vec<String>)> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut line = 1;
+    let mut record_line = 1;
+    let mut chars = content.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', true) => quoted = false,
+            ('"', false) if field.is_empty() => quoted = true,
+            (',', false) => record.push(std::mem::take(&mut field)),
+            ('\r', false) => {}
+            ('\n', false) => {
+                record.push(std::mem::take(&mut field));
+                if record.iter().any(|field| !field.is_empty()) {
+                    records.push((record_line, std::mem::take(&mut record)));
+                }
+                record.clear();
+                line += 1;
+                record_line = line;
+            }
+            (c, _) => {
+                if c == '\n' {
+                    line += 1;
+                }
+                field.push(c);
+            }
+        }
+    }
+
+    record.push(field);
+    if record.iter().any(|field| !field.is_empty()) {
+        records.push((record_line, record));
+    }
+
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::{export, ExportFormat};
+
+    #[test]
+    fn markdown_checklists_and_tags() {
+        let parsed = parse(
+            "# Sprint\n\n- [ ] write docs #docs @work\n  - [x] intro\n  - [ ] api\n- [x] ship\nnotes",
+            ImportFormat::Markdown,
+        );
+
+        assert_eq!(parsed.tasks.len(), 2);
+        assert_eq!(parsed.tasks[0].name, "write docs");
+        assert_eq!(parsed.tasks[0].tags, vec!["#docs", "@work"]);
+        assert_eq!(parsed.tasks[0].checklist.len(), 2);
+        assert!(parsed.tasks[0].checklist[0].done);
+        assert!(parsed.tasks[1].complete);
+    }
+
+    #[test]
+    fn todo_txt_fields() {
+        let parsed = parse(
+            "(A) 2024-04-20 call mom +family @phone due:2024-05-03\n\
+             x 2024-05-02 2024-05-01 pay rent pri:C\n\
+             \n\
+             (B)\n",
+            ImportFormat::TodoTxt,
+        );
+
+        let first = &parsed.tasks[0];
+        assert_eq!(first.name, "call mom");
+        assert_eq!(first.priority, 9);
+        assert_eq!(first.tags, vec!["#family", "@phone"]);
+        assert_eq!(first.created.format("%Y-%m-%d").to_string(), "2024-04-20");
+        assert!(first.due.is_some());
+
+        let second = &parsed.tasks[1];
+        assert!(second.complete);
+        assert_eq!(second.priority, 7);
+        assert_eq!(
+            second.completed.unwrap().format("%Y-%m-%d").to_string(),
+            "2024-05-02"
+        );
+
+        assert_eq!(parsed.problems, vec!["line 4: no task text"]);
+    }
+
+    #[test]
+    fn csv_with_header_mapping() {
+        let parsed = parse(
+            "Priority,Name,Extra,complete\n3,\"buy milk, eggs\",x,yes\n,\"multi\nline\",,no\nx,bad,,\n",
+            ImportFormat::Csv,
+        );
+
+        assert_eq!(parsed.tasks.len(), 2);
+        assert_eq!(parsed.tasks[0].name, "buy milk, eggs");
+        assert_eq!(parsed.tasks[0].priority, 3);
+        assert!(parsed.tasks[0].complete);
+        assert_eq!(parsed.tasks[1].name, "multi\nline");
+        assert_eq!(
+            parsed.problems,
+            vec!["line 5: priority 'x' is not a number"]
+        );
+    }
+
+    #[test]
+    fn csv_export_reads_back() {
+        let mut task = new_task("write \"docs\"");
+        task.description = "one, two".to_string();
+        task.tags = vec!["#docs".to_string()];
+        task.checklist = parse_checklist("[x] a\n[ ] b");
+
+        let csv = export(&[&task], ExportFormat::Csv);
+        let parsed = parse(&csv, ImportFormat::Csv);
+
+        assert!(parsed.problems.is_empty());
+        assert_eq!(parsed.tasks[0].name, task.name);
+        assert_eq!(parsed.tasks[0].description, task.description);
+        assert_eq!(parsed.tasks[0].tags, task.tags);
+        assert_eq!(parsed.tasks[0].checklist.len(), 2);
+        assert_eq!(parsed.tasks[0].created, task.created);
+    }
+
+    #[test]
+    fn dedupe_ignores_case() {
+        let existing = [new_task("Buy milk")];
+        let mut tasks = vec![new_task("buy milk "), new_task("a"), new_task("A")];
+
+        let dropped = dedupe_by_name(&mut tasks, existing.iter());
+
+        assert_eq!(dropped, vec!["buy milk", "A"]);
+        assert_eq!(tasks.len(), 1);
+    }
+}
@@ -0,0 +1,767 @@
+use std::{collections::HashMap, fmt, fs, path::Path};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use strum::IntoEnumIterator;
+use strum_macros::{Display, EnumIter, EnumString};
+
+use crate::{export::ExportFormat, MenuItem};
+
+/// Everything a key can be bound to outside of text input
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Display, EnumIter, EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum Action {
+    NextTab,
+    PrevTab,
+    Quit,
+    Undo,
+    Redo,
+    RestoreBackup,
+    NextTheme,
+    Help,
+    Down,
+    Up,
+    ToggleComplete,
+    CycleSort,
+    RaisePriorityFilter,
+    LowerPriorityFilter,
+    ToggleOpenOnly,
+    Search,
+    NextMatch,
+    PrevMatch,
+    ClearSearch,
+    NextTag,
+    ClearTag,
+    MoveUp,
+    MoveDown,
+    FocusChecklist,
+    Export,
+    AddTask,
+    EditTask,
+    DeleteTask,
+    AddNote,
+    EditNote,
+    DeleteNote,
+    ScrollDown,
+    ScrollUp,
+    OpenTask,
+    NextField,
+    PrevField,
+    Save,
+    NewLine,
+    Cancel,
+    KeepSearch,
+    Yes,
+    No,
+    ExportMarkdown,
+    ExportCsv,
+    ExportTodoTxt,
+    ToggleItem,
+    Back,
+    ToggleKind,
+}
+
+impl Action {
+    /// What the action does, as shown in the footer and the help screen
+    pub fn describe(self) -> &'static str {
+        match self {
+            Action::NextTab => "next tab",
+            Action::PrevTab => "previous tab",
+            Action::Quit => "quit",
+            Action::Undo => "undo",
+            Action::Redo => "redo",
+            Action::RestoreBackup => "restore backup",
+            Action::NextTheme => "next theme",
+            Action::Help => "help",
+            Action::Down => "down",
+            Action::Up => "up",
+            Action::ToggleComplete => "toggle done",
+            Action::CycleSort => "sort",
+            Action::RaisePriorityFilter => "raise min priority",
+            Action::LowerPriorityFilter => "lower min priority",
+            Action::ToggleOpenOnly => "open only",
+            Action::Search => "search",
+            Action::NextMatch => "next match",
+            Action::PrevMatch => "previous match",
+            Action::ClearSearch => "clear search",
+            Action::NextTag => "next tag",
+            Action::ClearTag => "clear tag",
+            Action::MoveUp => "move up",
+            Action::MoveDown => "move down",
+            Action::FocusChecklist => "checklist",
+            Action::Export => "export",
+            Action::AddTask | Action::AddNote => "add",
+            Action::EditTask | Action::EditNote => "edit",
+            Action::DeleteTask | Action::DeleteNote => "delete",
+            Action::ScrollDown => "scroll down",
+            Action::ScrollUp => "scroll up",
+            Action::OpenTask => "open",
+            Action::NextField => "next field",
+            Action::PrevField => "previous field",
+            Action::Save => "save",
+            Action::NewLine => "new line",
+            Action::Cancel => "cancel",
+            Action::KeepSearch => "keep search",
+            Action::Yes => "yes",
+            Action::No => "no",
+            Action::ExportMarkdown => "Markdown",
+            Action::ExportCsv => "CSV",
+            Action::ExportTodoTxt => "todo.txt",
+            Action::ToggleItem => "toggle item",
+            Action::Back => "back",
+            Action::ToggleKind => "toggle kind",
+        }
+    }
+
+    /// The format an action of the export popup picks
+    pub fn export_format(self) -> Option<ExportFormat> {
+        match self {
+            Action::ExportMarkdown => Some(ExportFormat::Markdown),
+            Action::ExportCsv => Some(ExportFormat::Csv),
+            Action::ExportTodoTxt => Some(ExportFormat::TodoTxt),
+            _ => None,
+        }
+    }
+}
+
+/// A group of bindings. A key is looked up in the layer of the active tab first, then in the
+/// `tasks` layer on the task tabs, and last in the `global` layer. While a mode like a form or
+/// the search is open, keys are only looked up in the layer of that mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Display, EnumIter, EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum Layer {
+    Global,
+    /// Shared by the Todo, Done and All tabs
+    Tasks,
+    Home,
+    Todo,
+    Done,
+    All,
+    Notes,
+    /// The task and the note form, every key that is not bound here is typed into the field
+    Form,
+    /// The kind field of the note form while it has focus, before the `form` layer
+    NoteKind,
+    /// Typing into the search box of a task list
+    Search,
+    Confirm,
+    Export,
+    /// The checklist of the selected task while it has focus
+    Checklist,
+}
+
+impl Layer {
+    /// Heading of the layer on the help screen
+    pub fn describe(self) -> &'static str {
+        match self {
+            Layer::Global => "Everywhere",
+            Layer::Tasks => "Task lists (Todo, Done, All)",
+            Layer::Home => "Home",
+            Layer::Todo => "Todo",
+            Layer::Done => "Done",
+            Layer::All => "All",
+            Layer::Notes => "Notes",
+            Layer::Form => "Task and note forms",
+            Layer::NoteKind => "Note kind",
+            Layer::Search => "Search",
+            Layer::Confirm => "Confirmation",
+            Layer::Export => "Export",
+            Layer::Checklist => "Checklist",
+        }
+    }
+
+    /// Whether the layer belongs to a mode instead of a tab
+    pub fn is_mode(self) -> bool {
+        matches!(
+            self,
+            Layer::Form
+                | Layer::NoteKind
+                | Layer::Search
+                | Layer::Confirm
+                | Layer::Export
+                | Layer::Checklist
+        )
+    }
+
+    /// Layers a key is looked up in on the given tab, most specific first
+    pub fn stack(tab: MenuItem) -> Vec<Layer> {
+        match tab {
+            MenuItem::Home => vec![Layer::Home, Layer::Global],
+            MenuItem::Todo => vec![Layer::Todo, Layer::Tasks, Layer::Global],
+            MenuItem::Done => vec![Layer::Done, Layer::Tasks, Layer::Global],
+            MenuItem::All => vec![Layer::All, Layer::Tasks, Layer::Global],
+            MenuItem::Notes => vec![Layer::Notes, Layer::Global],
+            MenuItem::Quit => vec![Layer::Global],
+        }
+    }
+}
+
+/// A key together with Ctrl / Alt, written like `j`, `J`, `Ctrl-r`, `Space` or `PageDown`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    /// Shift is already part of the character for letters and symbols and of `BackTab`,
+    /// so it is dropped to make `J` match however the terminal reports it
+    fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let modifiers = match code {
+            KeyCode::Char(_) | KeyCode::BackTab => modifiers - KeyModifiers::SHIFT,
+            _ => modifiers,
+        };
+
+        Self { code, modifiers }
+    }
+
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let mut modifiers = KeyModifiers::empty();
+        let mut rest = input;
+
+        // a lone `-` is the minus key, `Ctrl--` is Ctrl and minus
+        while let Some((prefix, key)) = rest.split_once('-').filter(|(_, key)| !key.is_empty()) {
+            modifiers |= match prefix.to_lowercase().as_str() {
+                "ctrl" | "c" => KeyModifiers::CONTROL,
+                "alt" | "a" | "m" => KeyModifiers::ALT,
+                "shift" | "s" => KeyModifiers::SHIFT,
+                _ => break,
+            };
+            rest = key;
+        }
+
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) if modifiers.contains(KeyModifiers::SHIFT) => {
+                KeyCode::Char(c.to_ascii_uppercase())
+            }
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match rest.to_lowercase().as_str() {
+                "space" => KeyCode::Char(' '),
+                "enter" | "return" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "tab" if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" | "ins" => KeyCode::Insert,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" | "pgup" => KeyCode::PageUp,
+                "pagedown" | "pgdn" => KeyCode::PageDown,
+                other => match other.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    Some(number @ 1..=12) => KeyCode::F(number),
+                    _ => return Err(format!("unknown key '{}'", input)),
+                },
+            },
+        };
+
+        Ok(Self::new(code, modifiers))
+    }
+}
+
+impl From<&KeyEvent> for KeyChord {
+    fn from(key: &KeyEvent) -> Self {
+        Self::new(key.code, key.modifiers)
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "Ctrl-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "Alt-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "Shift-")?;
+        }
+
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::BackTab => write!(f, "Shift-Tab"),
+            KeyCode::PageUp => write!(f, "PageUp"),
+            KeyCode::PageDown => write!(f, "PageDown"),
+            KeyCode::F(number) => write!(f, "F{}", number),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+/// Bindings shipped with toodles, per layer
+const DEFAULT_BINDINGS: &[(Layer, Action, &[&str])] = &[
+    (Layer::Global, Action::NextTab, &["l", "L", "Right"]),
+    (Layer::Global, Action::PrevTab, &["h", "H", "Left"]),
+    (Layer::Global, Action::Quit, &["q", "Q"]),
+    (Layer::Global, Action::Undo, &["u"]),
+    (Layer::Global, Action::Redo, &["Ctrl-r"]),
+    (Layer::Global, Action::RestoreBackup, &["R"]),
+    (Layer::Global, Action::NextTheme, &["Ctrl-t"]),
+    (Layer::Global, Action::Help, &["?"]),
+    (Layer::Home, Action::Down, &["j", "Down"]),
+    (Layer::Home, Action::Up, &["k", "Up"]),
+    (Layer::Home, Action::OpenTask, &["Enter"]),
+    (Layer::Tasks, Action::Down, &["j", "Down"]),
+    (Layer::Tasks, Action::Up, &["k", "Up"]),
+    (Layer::Tasks, Action::ToggleComplete, &["Space", "c", "C"]),
+    (Layer::Tasks, Action::CycleSort, &["s"]),
+    (Layer::Tasks, Action::RaisePriorityFilter, &["+"]),
+    (Layer::Tasks, Action::LowerPriorityFilter, &["-"]),
+    (Layer::Tasks, Action::ToggleOpenOnly, &["i"]),
+    (Layer::Tasks, Action::Search, &["/"]),
+    (Layer::Tasks, Action::NextMatch, &["n"]),
+    (Layer::Tasks, Action::PrevMatch, &["N"]),
+    (Layer::Tasks, Action::ClearSearch, &["Esc"]),
+    (Layer::Tasks, Action::NextTag, &["t"]),
+    (Layer::Tasks, Action::ClearTag, &["T"]),
+    (Layer::Tasks, Action::MoveUp, &["K"]),
+    (Layer::Tasks, Action::MoveDown, &["J"]),
+    (Layer::Tasks, Action::FocusChecklist, &["Tab"]),
+    (Layer::Tasks, Action::Export, &["x"]),
+    (Layer::Tasks, Action::EditTask, &["e", "E"]),
+    (Layer::Tasks, Action::DeleteTask, &["d", "D"]),
+    (Layer::Todo, Action::AddTask, &["a", "A"]),
+    (Layer::Notes, Action::Down, &["j", "Down"]),
+    (Layer::Notes, Action::Up, &["k", "Up"]),
+    (Layer::Notes, Action::AddNote, &["a", "A"]),
+    (Layer::Notes, Action::EditNote, &["e", "E"]),
+    (Layer::Notes, Action::DeleteNote, &["d", "D"]),
+    (Layer::Notes, Action::ScrollDown, &["J", "PageDown"]),
+    (Layer::Notes, Action::ScrollUp, &["K", "PageUp"]),
+    (Layer::Form, Action::NextField, &["Tab"]),
+    (Layer::Form, Action::PrevField, &["Shift-Tab"]),
+    (Layer::Form, Action::Save, &["Enter"]),
+    (Layer::Form, Action::NewLine, &["Alt-Enter", "Ctrl-n"]),
+    (Layer::Form, Action::Cancel, &["Esc"]),
+    (
+        Layer::NoteKind,
+        Action::ToggleKind,
+        &["Space", "Left", "Right"],
+    ),
+    (Layer::Search, Action::KeepSearch, &["Enter"]),
+    (Layer::Search, Action::ClearSearch, &["Esc"]),
+    (Layer::Confirm, Action::Yes, &["y", "Y"]),
+    (Layer::Confirm, Action::No, &["n", "N", "Esc"]),
+    (Layer::Export, Action::ExportMarkdown, &["m", "M"]),
+    (Layer::Export, Action::ExportCsv, &["c", "C"]),
+    (Layer::Export, Action::ExportTodoTxt, &["t", "T"]),
+    (Layer::Export, Action::Cancel, &["Esc"]),
+    (Layer::Checklist, Action::Down, &["j", "Down"]),
+    (Layer::Checklist, Action::Up, &["k", "Up"]),
+    (
+        Layer::Checklist,
+        Action::ToggleItem,
+        &["Space", "x", "X", "c", "C"],
+    ),
+    (Layer::Checklist, Action::Back, &["Tab", "Esc"]),
+];
+
+/// Key config as it is written in the file: layer name to action name to keys
+type KeyConfig = HashMap<String, HashMap<String, Vec<String>>>;
+
+/// Maps key chords to actions, layer by layer
+pub struct Keymap {
+    layers: HashMap<Layer, Vec<(KeyChord, Action)>>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let mut layers: HashMap<Layer, Vec<(KeyChord, Action)>> = HashMap::new();
+
+        for (layer, action, keys) in DEFAULT_BINDINGS {
+            for key in *keys {
+                let chord = KeyChord::parse(key).expect("Default bindings use valid keys");
+                layers.entry(*layer).or_default().push((chord, *action));
+            }
+        }
+
+        Self { layers }
+    }
+}
+
+impl Keymap {
+    /// The default bindings with the ones from the config file applied on top.
+    ///
+    /// A missing file just means defaults. Returns every problem found in the file and every
+    /// conflicting binding, so they can be reported at startup; the rest of the file still
+    /// applies.
+    pub fn load(path: &Path) -> (Self, Vec<String>) {
+        let mut keymap = Keymap::default();
+
+        let raw = match fs::read_to_string(path) {
+            Ok(raw) => raw,
+            Err(_) => return (keymap, Vec::new()),
+        };
+
+        let is_json = path.extension().map(|ext| ext == "json").unwrap_or(false);
+        let config: Result<KeyConfig, String> = if is_json {
+            serde_json::from_str(&raw).map_err(|e| e.to_string())
+        } else {
+            toml::from_str(&raw).map_err(|e| e.to_string())
+        };
+
+        let mut problems = match config {
+            Ok(config) => keymap.apply(&config),
+            Err(e) => vec![format!("cannot read {}: {}", path.display(), e)],
+        };
+        problems.extend(keymap.conflicts());
+
+        (keymap, problems)
+    }
+
+    /// Replace the default keys of every action named in the config, an empty list unbinds it
+    fn apply(&mut self, config: &KeyConfig) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        for (layer_name, actions) in config {
+            let layer: Layer = match layer_name.parse() {
+                Ok(layer) => layer,
+                Err(_) => {
+                    problems.push(format!("unknown key layer [{}]", layer_name));
+                    continue;
+                }
+            };
+
+            for (action_name, keys) in actions {
+                let action: Action = match action_name.parse() {
+                    Ok(action) => action,
+                    Err(_) => {
+                        problems.push(format!("unknown action '{}' in [{}]", action_name, layer));
+                        continue;
+                    }
+                };
+
+                let bindings = self.layers.entry(layer).or_default();
+                bindings.retain(|(_, bound)| *bound != action);
+
+                for key in keys {
+                    match KeyChord::parse(key) {
+                        Ok(chord) => bindings.push((chord, action)),
+                        Err(e) => problems.push(format!("{} for {} in [{}]", e, action, layer)),
+                    }
+                }
+            }
+        }
+
+        problems
+    }
+
+    /// Keys bound to two actions in the same layer, and tab keys that hide a key of a layer
+    /// below them
+    fn conflicts(&self) -> Vec<String> {
+        let mut conflicts = Vec::new();
+
+        for layer in Layer::iter() {
+            let bindings = self.bindings(layer);
+
+            for (index, (chord, action)) in bindings.iter().enumerate() {
+                if let Some((_, other)) = bindings[..index]
+                    .iter()
+                    .find(|(earlier, other)| earlier == chord && other != action)
+                {
+                    conflicts.push(format!(
+                        "{} is bound to both {} and {} in [{}]",
+                        chord, other, action, layer
+                    ));
+                }
+            }
+        }
+
+        for tab in MenuItem::iter() {
+            let stack = Layer::stack(tab);
+
+            for (position, upper) in stack.iter().enumerate() {
+                for (chord, action) in self.bindings(*upper) {
+                    let hidden = stack[position + 1..].iter().find_map(|lower| {
+                        self.bindings(*lower)
+                            .iter()
+                            .find(|(other_chord, other)| other_chord == chord && other != action)
+                            .map(|(_, other)| (lower, other))
+                    });
+
+                    if let Some((lower, other)) = hidden {
+                        let conflict = format!(
+                            "{} for {} in [{}] hides {} in [{}]",
+                            chord, action, upper, other, lower
+                        );
+
+                        if !conflicts.contains(&conflict) {
+                            conflicts.push(conflict);
+                        }
+                    }
+                }
+            }
+        }
+
+        conflicts
+    }
+
+    /// The action a key triggers on the given tab
+    pub fn action(&self, tab: MenuItem, key: &KeyEvent) -> Option<Action> {
+        let chord = KeyChord::from(key);
+
+        Layer::stack(tab).into_iter().find_map(|layer| {
+            self.bindings(layer)
+                .iter()
+                .find(|(bound, _)| *bound == chord)
+                .map(|(_, action)| *action)
+        })
+    }
+
+    /// The action a key triggers in an open mode, other layers are not consulted
+    pub fn mode_action(&self, mode: Layer, key: &KeyEvent) -> Option<Action> {
+        let chord = KeyChord::from(key);
+
+        self.bindings(mode)
+            .iter()
+            .find(|(bound, _)| *bound == chord)
+            .map(|(_, action)| *action)
+    }
+
+    /// Keys bound to an action in one layer
+    pub fn keys(&self, layer: Layer, action: Action) -> Vec<KeyChord> {
+        self.bindings(layer)
+            .iter()
+            .filter(|(_, bound)| *bound == action)
+            .map(|(chord, _)| *chord)
+            .collect()
+    }
+
+    /// Keys that trigger an action on a tab, without the ones hidden by a more specific layer
+    pub fn tab_keys(&self, tab: MenuItem, action: Action) -> Vec<KeyChord> {
+        self.tab_help(tab)
+            .into_iter()
+            .find(|(_, bound)| *bound == action)
+            .map(|(keys, _)| keys)
+            .unwrap_or_default()
+    }
+
+    /// Bindings of one layer in the order they were defined
+    pub fn bindings(&self, layer: Layer) -> &[(KeyChord, Action)] {
+        self.layers.get(&layer).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Keys of one layer grouped per action, for the help screen
+    pub fn layer_help(&self, layer: Layer) -> Vec<(Vec<KeyChord>, Action)> {
+        group_by_action(self.bindings(layer).iter().copied())
+    }
+
+    /// Keys that work on a tab grouped per action, the most specific layer first. Keys hidden by
+    /// a more specific layer are left out.
+    pub fn tab_help(&self, tab: MenuItem) -> Vec<(Vec<KeyChord>, Action)> {
+        let mut seen = Vec::new();
+        let live = Layer::stack(tab)
+            .into_iter()
+            .flat_map(|layer| self.bindings(layer).iter().copied())
+            .filter(|(chord, _)| {
+                let hidden = seen.contains(chord);
+                seen.push(*chord);
+                !hidden
+            })
+            .collect::<Vec<_>>();
+
+        group_by_action(live.into_iter())
+    }
+}
+
+/// Keys joined for display, like `j/Down`
+pub fn format_keys(keys: &[KeyChord]) -> String {
+    keys.iter()
+        .map(|key| key.to_string())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Keys and what they do, like `j/Down: down`
+pub fn format_hint(keys: &[KeyChord], action: Action) -> String {
+    format!("{}: {}", format_keys(keys), action.describe())
+}
+
+/// Collect the keys of each action, actions in the order they first appear
+fn group_by_action(
+    bindings: impl Iterator<Item = (KeyChord, Action)>,
+) -> Vec<(Vec<KeyChord>, Action)> {
+    let mut grouped: Vec<(Vec<KeyChord>, Action)> = Vec::new();
+
+    for (chord, action) in bindings {
+        match grouped.iter_mut().find(|(_, other)| *other == action) {
+            Some((keys, _)) => keys.push(chord),
+            None => grouped.push((vec![chord], action)),
+        }
+    }
+
+    grouped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    fn config(toml: &str) -> KeyConfig {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn chords_parse_and_print() {
+        for input in [
+            "j",
+            "J",
+            "Ctrl-r",
+            "Space",
+            "PageDown",
+            "Shift-Tab",
+            "-",
+            "Ctrl--",
+            "F5",
+        ] {
+            assert_eq!(KeyChord::parse(input).unwrap().to_string(), input);
+        }
+
+        assert_eq!(KeyChord::parse("shift-a").unwrap().to_string(), "A");
+        assert!(KeyChord::parse("Hyper-x").is_err());
+        assert!(KeyChord::parse("nope").is_err());
+    }
+
+    #[test]
+    fn shift_is_part_of_the_character() {
+        let keymap = Keymap::default();
+
+        assert_eq!(
+            keymap.action(
+                MenuItem::Todo,
+                &key(KeyCode::Char('J'), KeyModifiers::SHIFT)
+            ),
+            Some(Action::MoveDown)
+        );
+    }
+
+    #[test]
+    fn tab_layers_come_before_global() {
+        let keymap = Keymap::default();
+        let j = key(KeyCode::Char('j'), KeyModifiers::NONE);
+        let a = key(KeyCode::Char('a'), KeyModifiers::NONE);
+        let d = key(KeyCode::Char('d'), KeyModifiers::NONE);
+
+        assert_eq!(keymap.action(MenuItem::Notes, &j), Some(Action::Down));
+        assert_eq!(keymap.action(MenuItem::Home, &j), Some(Action::Down));
+        assert_eq!(keymap.action(MenuItem::Todo, &a), Some(Action::AddTask));
+        assert_eq!(keymap.action(MenuItem::Done, &a), None);
+        assert_eq!(keymap.action(MenuItem::Done, &d), Some(Action::DeleteTask));
+        assert_eq!(keymap.action(MenuItem::Notes, &d), Some(Action::DeleteNote));
+        assert_eq!(
+            keymap.action(MenuItem::Home, &key(KeyCode::Char('q'), KeyModifiers::NONE)),
+            Some(Action::Quit)
+        );
+    }
+
+    #[test]
+    fn tab_help_starts_with_the_tab_layer() {
+        let help = Keymap::default().tab_help(MenuItem::Todo);
+
+        assert_eq!(help[0].1, Action::AddTask);
+        assert_eq!(format_keys(&help[0].0), "a/A");
+        assert!(help.iter().any(|(_, action)| *action == Action::Quit));
+        assert!(!help.iter().any(|(_, action)| *action == Action::AddNote));
+    }
+
+    #[test]
+    fn tab_help_leaves_out_hidden_keys() {
+        let mut keymap = Keymap::default();
+        keymap.apply(&config("[todo]\nadd_task = [\"q\"]\n"));
+
+        let help = keymap.tab_help(MenuItem::Todo);
+        let quit = help.iter().find(|(_, action)| *action == Action::Quit);
+
+        assert_eq!(format_keys(&quit.unwrap().0), "Q");
+    }
+
+    #[test]
+    fn defaults_have_no_conflicts() {
+        assert_eq!(Keymap::default().conflicts(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn config_replaces_bindings_of_an_action() {
+        let mut keymap = Keymap::default();
+        let problems = keymap.apply(&config("[global]\nquit = [\"Ctrl-q\"]\n"));
+
+        assert!(problems.is_empty());
+        assert_eq!(
+            keymap.action(MenuItem::Todo, &key(KeyCode::Char('q'), KeyModifiers::NONE)),
+            None
+        );
+        assert_eq!(
+            keymap.action(
+                MenuItem::Todo,
+                &key(KeyCode::Char('q'), KeyModifiers::CONTROL)
+            ),
+            Some(Action::Quit)
+        );
+    }
+
+    #[test]
+    fn problems_and_conflicts_are_reported() {
+        let mut keymap = Keymap::default();
+        let problems = keymap.apply(&config(
+            "[todo]\nadd_task = [\"q\", \"Hyper-x\"]\nfly = [\"f\"]\n[nowhere]\nquit = []\n",
+        ));
+
+        assert_eq!(problems.len(), 3);
+        assert_eq!(
+            keymap.conflicts(),
+            vec!["q for add_task in [todo] hides quit in [global]"]
+        );
+    }
+
+    #[test]
+    fn modes_only_use_their_own_layer() {
+        let keymap = Keymap::default();
+        let esc = key(KeyCode::Esc, KeyModifiers::NONE);
+        let q = key(KeyCode::Char('q'), KeyModifiers::NONE);
+
+        assert_eq!(keymap.mode_action(Layer::Form, &esc), Some(Action::Cancel));
+        assert_eq!(
+            keymap.mode_action(Layer::Search, &esc),
+            Some(Action::ClearSearch)
+        );
+        assert_eq!(keymap.mode_action(Layer::Form, &q), None);
+
+        // space toggles the note kind but is typed into every other field
+        let space = key(KeyCode::Char(' '), KeyModifiers::NONE);
+        assert_eq!(
+            keymap.mode_action(Layer::NoteKind, &space),
+            Some(Action::ToggleKind)
+        );
+        assert_eq!(keymap.mode_action(Layer::Form, &space), None);
+        assert_eq!(
+            keymap.mode_action(Layer::Form, &key(KeyCode::Enter, KeyModifiers::ALT)),
+            Some(Action::NewLine)
+        );
+    }
+
+    #[test]
+    fn keys_of_an_action_follow_the_config() {
+        let mut keymap = Keymap::default();
+        keymap.apply(&config("[checklist]\nback = [\"Backspace\"]\n"));
+
+        assert_eq!(
+            format_keys(&keymap.keys(Layer::Checklist, Action::Back)),
+            "Backspace"
+        );
+        assert_eq!(
+            format_keys(&keymap.tab_keys(MenuItem::Todo, Action::AddTask)),
+            "a/A"
+        );
+        assert!(keymap.tab_keys(MenuItem::Done, Action::AddTask).is_empty());
+    }
+}
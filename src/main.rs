@@ -1,10 +1,28 @@
-const DB_PATH: &str = "./data/db.json";
-
+mod backup;
+mod checklist;
+mod cli;
+mod config;
+mod dashboard;
+mod due;
+mod export;
+mod form;
+mod history;
+mod import;
+mod keymap;
+mod modal;
 mod renders;
+mod schema;
+mod search;
+mod store;
+mod tags;
+mod theme;
 mod user_input;
+mod view;
 
 use std::{
-    fs, io, process,
+    env, io, panic,
+    path::Path,
+    process,
     sync::{
         mpsc::{channel, Receiver, Sender},
         Mutex,
@@ -20,29 +38,79 @@ use crossterm::{
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use rand::Rng;
+use form::{NoteField, NoteForm, TaskForm};
+use keymap::{format_hint, Action, KeyChord, Keymap, Layer};
+use modal::Modal;
 use renders::{
-    centered_rect, render_static_home, render_static_test_page, render_todo_page, split_main_window,
+    centered_rect, render_confirm_popup, render_error_page, render_error_popup,
+    render_export_popup, render_footer, render_help, render_home, render_note_form,
+    render_notes_page, render_status_bar, render_tag_sidebar, render_task_form, render_todo_page,
+    split_main_window,
 };
 use serde::{Deserialize, Serialize};
+use store::TaskStore;
+use theme::Themes;
+use view::ListView;
 
 use crossterm::event::Event as CrossEvent;
 use strum_macros::Display;
+use thiserror::Error;
 use tui::{
     backend::CrosstermBackend,
-    layout::{Alignment, Constraint, Direction, Layout},
-    style::{Color, Modifier, Style},
+    layout::{Constraint, Direction, Layout},
+    style::{Modifier, Style},
     text::{Span, Spans},
-    widgets::{Block, BorderType, Borders, Clear, ListState, Paragraph, Tabs},
+    widgets::{Block, Borders, ListState, Tabs},
     Terminal,
 };
 
 use strum::{EnumIter, IntoEnumIterator};
 
-static SHOW_POPUP: Mutex<bool> = Mutex::new(false);
 static TERMINATION_LOCK: Mutex<bool> = Mutex::new(false);
+/// Message in the status bar about what the last key did
+static STATUS_MESSAGE: Mutex<Option<StatusMessage>> = Mutex::new(None);
+
+/// How long a status message is shown at full strength before it starts to fade
+const STATUS_FADE: Duration = Duration::from_secs(3);
+/// How long a status message is shown at all
+const STATUS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A status message and when it was shown, so it can fade out
+#[derive(Clone, Debug)]
+pub struct StatusMessage {
+    pub text: String,
+    pub shown: Instant,
+}
+
+impl StatusMessage {
+    /// Whether the message is in its last moments and drawn dimmed
+    pub fn is_fading(&self) -> bool {
+        self.shown.elapsed() >= STATUS_FADE
+    }
+}
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let cli::Args { db, command } = cli::parse_args(&args).unwrap_or_else(|message| {
+        eprintln!("{}\n\n{}", message, cli::USAGE);
+        process::exit(2);
+    });
+
+    if let Err(e) = config::init_db_path(db) {
+        eprintln!("Cannot open database: {}", e);
+        process::exit(1);
+    }
+
+    // a command on the command line runs without taking over the terminal
+    if let Some(command) = command {
+        if let Err(e) = cli::run(command) {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        return Ok(());
+    }
+
     // arc that will handle breaking and cleaning up maybe???
     // let termination_lock = Arc::new(Mutex::new(false));
 
@@ -79,10 +147,16 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Create menu stuff
     let menu_titles = create_menu_vector();
 
-    let mut active_menu_item = MenuItem::Home;
-
     let mut stdout_value = io::stdout();
 
+    // put the terminal back into a usable state before a panic message is printed
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let _ = disable_raw_mode();
+        let _ = execute!(io::stdout(), DisableMouseCapture, LeaveAlternateScreen);
+        default_hook(info);
+    }));
+
     execute!(stdout_value, EnterAlternateScreen, EnableMouseCapture)
         .expect("I cannot do that steven");
     enable_raw_mode().expect("Cannot enable raw mode");
@@ -94,9 +168,38 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         process::exit(5);
     }
 
-    // init list states here for stateful widgets
-    let mut todo_list_state = ListState::default();
-    todo_list_state.select(Some(0));
+    // load the tasks once, from here on they are only changed in memory and flushed on change
+    let store = match TaskStore::load() {
+        Ok(store) => {
+            // the file just parsed, so this is a good state to keep a backup of
+            if let Err(e) = backup::create_backup() {
+                show_error(&e);
+            }
+            store
+        }
+        Err(e) => {
+            show_error(&e);
+            TaskStore::unloaded(&e)
+        }
+    };
+
+    let (keymap, problems) = Keymap::load(&config::keymap_path());
+    if !problems.is_empty() {
+        modal::open(Modal::Error(format!(
+            "Problems in the key bindings:\n{}",
+            problems.join("\n")
+        )));
+    }
+
+    let (themes, problems) = Themes::load(&config::themes_path());
+    if !problems.is_empty() {
+        modal::open(Modal::Error(format!(
+            "Problems in the themes:\n{}",
+            problems.join("\n")
+        )));
+    }
+
+    let mut app = App::new(store, keymap, themes);
 
     // implement the render loop
 
@@ -104,17 +207,17 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         terminal
             .draw(|frame| {
                 let sub_win = split_main_window(frame);
-
-                let static_widget = Paragraph::new("STATIC FOOTER THAT IS HERE!!\n\nYEEEE")
-                    .style(Style::default().fg(Color::Magenta))
-                    .alignment(Alignment::Center)
-                    .block(
-                        Block::default()
-                            .borders(Borders::ALL)
-                            .style(Style::default().fg(Color::DarkGray))
-                            .title("DEF WIDGET")
-                            .border_type(BorderType::Thick),
-                    );
+                let theme = app.themes.current().clone();
+
+                let footer = render_footer(footer_hints(&app), &theme);
+                let status = STATUS_MESSAGE.lock().unwrap().clone();
+                let (message, counts) = render_status_bar(
+                    status.as_ref(),
+                    &app.store,
+                    &app.view,
+                    app.active_menu_item,
+                    &theme,
+                );
 
                 // Create menu items from hardcoded vector, split each one off at the first letter and
                 // give it a different highlighting to show what shortcut corresponds to it... Could be
@@ -128,40 +231,79 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                             Span::styled(
                                 first,
                                 Style::default()
-                                    .fg(Color::Blue)
+                                    .fg(theme.tab_key)
                                     .add_modifier(Modifier::RAPID_BLINK),
                             ),
-                            Span::styled(rest, Style::default().fg(Color::Cyan)),
+                            Span::styled(rest, Style::default().fg(theme.tab_text)),
                         ])
                     })
                     .collect();
 
                 let tabs = Tabs::new(menu)
-                    .select(active_menu_item.into())
+                    .select(app.active_menu_item.into())
                     .block(Block::default().title("Menu").borders(Borders::ALL))
-                    .style(Style::default().fg(Color::White))
+                    .style(theme.text())
                     .highlight_style(
                         Style::default()
-                            .fg(Color::Yellow)
+                            .fg(theme.tab_selected)
                             .add_modifier(Modifier::UNDERLINED),
                     )
                     .divider(Span::raw("||"));
 
-                frame.render_widget(static_widget, sub_win[2]);
+                let status_bar = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([Constraint::Min(10), Constraint::Length(60)].as_ref())
+                    .split(sub_win[2]);
+
+                frame.render_widget(message, status_bar[0]);
+                frame.render_widget(counts, status_bar[1]);
+                frame.render_widget(footer, sub_win[3]);
 
                 frame.render_widget(tabs, sub_win[0]);
 
                 // Handle input here
 
                 /////////////////////////////////////////////////////////////////////////////////
-                user_input::handle_keys(&rx, &mut active_menu_item, &mut todo_list_state);
+                user_input::handle_keys(&rx, &mut app);
                 //////////////////////////////////////////////////////////////////////////////
 
+                if let Err(e) = app.store.flush() {
+                    show_error(&e);
+                }
+
                 // Decide what to render on the main screen here...
 
-                match active_menu_item {
-                    MenuItem::Home => frame.render_widget(render_static_home(), sub_win[1]),
-                    MenuItem::Todo => {
+                let App {
+                    store,
+                    active_menu_item,
+                    list_states,
+                    task_form,
+                    view,
+                    checklist_focus,
+                    home_list_state,
+                    note_list_state,
+                    note_form,
+                    note_scroll,
+                    keymap,
+                    ..
+                } = &mut app;
+
+                match *active_menu_item {
+                    MenuItem::Home => {
+                        if let Some(error) = store.load_error() {
+                            frame.render_widget(
+                                render_error_page(error, keymap, *active_menu_item, &theme),
+                                sub_win[1],
+                            );
+                        } else {
+                            render_home(frame, store, home_list_state, keymap, sub_win[1], &theme);
+                        }
+                    }
+                    MenuItem::Todo | MenuItem::Done | MenuItem::All => {
+                        let list_state = list_states
+                            .for_tab(*active_menu_item)
+                            .expect("Task tabs always have a list state");
+
                         // separate subwindow into smaller subwindows..
                         let todo_subwin = Layout::default()
                             .direction(Direction::Horizontal)
@@ -170,6 +312,14 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                             )
                             .split(sub_win[1]);
 
+                        // the tag sidebar sits below the task list
+                        let list_and_tags = Layout::default()
+                            .direction(Direction::Vertical)
+                            .constraints(
+                                [Constraint::Percentage(70), Constraint::Percentage(30)].as_ref(),
+                            )
+                            .split(todo_subwin[0]);
+
                         // split bigger window into vertical arrangement to have a details area...
 
                         let todo_detail = Layout::default()
@@ -179,39 +329,109 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                             )
                             .split(todo_subwin[1]);
 
-                        let (left, (right_top, right_bottom)) = render_todo_page(&todo_list_state);
-
-                        frame.render_stateful_widget(left, todo_subwin[0], &mut todo_list_state);
-                        frame.render_widget(right_top, todo_detail[0]);
-                        frame.render_widget(right_bottom, todo_detail[1]);
+                        if let Some(error) = store.load_error() {
+                            frame.render_widget(
+                                render_error_page(error, keymap, *active_menu_item, &theme),
+                                sub_win[1],
+                            );
+                        } else {
+                            let (left, (right_top, right_bottom)) = render_todo_page(
+                                store,
+                                view,
+                                list_state,
+                                *active_menu_item,
+                                *checklist_focus,
+                                keymap,
+                                &theme,
+                            );
+
+                            let (tags, mut tag_state) =
+                                render_tag_sidebar(store, view, keymap, *active_menu_item, &theme);
+
+                            frame.render_stateful_widget(left, list_and_tags[0], list_state);
+                            frame.render_stateful_widget(tags, list_and_tags[1], &mut tag_state);
+                            frame.render_widget(right_top, todo_detail[0]);
+                            frame.render_widget(right_bottom, todo_detail[1]);
+                        }
+                    }
+                    MenuItem::Notes => {
+                        if let Some(error) = store.load_error() {
+                            frame.render_widget(
+                                render_error_page(error, keymap, *active_menu_item, &theme),
+                                sub_win[1],
+                            );
+                        } else {
+                            let notes_subwin = Layout::default()
+                                .direction(Direction::Horizontal)
+                                .constraints(
+                                    [Constraint::Percentage(30), Constraint::Percentage(70)]
+                                        .as_ref(),
+                                )
+                                .split(sub_win[1]);
+
+                            let (list, detail) = render_notes_page(
+                                store,
+                                note_list_state,
+                                note_scroll,
+                                notes_subwin[1],
+                                keymap,
+                                &theme,
+                            );
+
+                            frame.render_stateful_widget(list, notes_subwin[0], note_list_state);
+                            frame.render_widget(detail, notes_subwin[1]);
+                        }
                     }
-                    MenuItem::Done => frame.render_widget(render_static_test_page(), sub_win[1]),
-                    MenuItem::All => frame.render_widget(render_static_test_page(), sub_win[1]),
-                    MenuItem::Notes => frame.render_widget(render_static_test_page(), sub_win[1]),
                     MenuItem::Quit => unreachable!(),
                 }
 
-                // Decide whether popups need to be drawn over the current screen...
-
-                let x = SHOW_POPUP.lock().unwrap();
-                if *x {
-                    let size = frame.size();
-
-                    let block = Block::default()
-                        .title(" Add New Todo ")
-                        .borders(Borders::ALL)
-                        .border_type(BorderType::Double);
-                    let area = centered_rect(90, 80, size);
-
-                    frame.render_widget(Clear, area);
-                    frame.render_widget(block, area);
+                // Draw the open popups over the current screen, the newest on top
+
+                for popup in modal::stack() {
+                    match popup {
+                        Modal::TaskForm => render_task_form(
+                            frame,
+                            task_form,
+                            keymap,
+                            centered_rect(90, 80, frame.size()),
+                            &theme,
+                        ),
+                        Modal::NoteForm => render_note_form(
+                            frame,
+                            note_form,
+                            keymap,
+                            centered_rect(90, 80, frame.size()),
+                            &theme,
+                        ),
+                        Modal::Confirm { message, .. } => render_confirm_popup(
+                            frame,
+                            &message,
+                            keymap,
+                            centered_rect(40, 20, frame.size()),
+                            &theme,
+                        ),
+                        Modal::Export => render_export_popup(
+                            frame,
+                            keymap,
+                            centered_rect(40, 20, frame.size()),
+                            &theme,
+                        ),
+                        Modal::Help => render_help(frame, keymap, frame.size(), &theme),
+                        Modal::Error(message) => render_error_popup(
+                            frame,
+                            &message,
+                            centered_rect(60, 30, frame.size()),
+                            &theme,
+                        ),
+                    }
                 }
             })
             .expect("BIIIG ERROR"); // END OF DRAW
 
         let x = TERMINATION_LOCK.lock().unwrap();
         if *x {
-            // TODO add saving and cleaning up data here?
+            // last chance to get pending changes onto disk
+            let saved = app.store.flush_on_exit();
 
             execute!(
                 terminal.backend_mut(),
@@ -222,75 +442,83 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             disable_raw_mode().expect("Cannot disable raw mode");
             terminal.show_cursor().expect("Cannot show cursor?? ");
             terminal.clear().expect("Cannot clear terminal");
+
+            if let Err(e) = saved {
+                eprintln!("Cannot save tasks: {}", e);
+            }
             return Ok(()); // Exit program
         }
         drop(x);
     }
 }
 
-fn add_new_test_task_to_list() -> Result<(), LocalError> {
-    // todo read db here and find the last entries ID....
-
-    let x = Task {
-        id: rand::thread_rng().gen_range(0, 100),
-        name: "NEW DEFAULT NAME".to_string(),
-        description: "NEW REALLY LONG HOPELLY NOT TRUNCATED DESCRIPTION THAT WILL HAVE TO BE EITHER TWEAKED OR FIXED OR SOMETHING LIKE THAT IN THE END..............   hOPEFULLY!! MAYBE WITH A FEW NEWLINES?   \n lEYTS SEE IF THIS RENDERS.... \n OR THIS MAYBE \n MAYBE HERE... ".to_string(),
-        complete: false,
-        priority:rand::thread_rng().gen_range(0, 10),
-        created: Local::now(),
-    };
-
-    if let Ok(mut data) = read_database() {
-        data.push(x);
-
-        write_database(&data);
-
-        Ok(())
-        // Ok(data)
-    } else {
-        Err(LocalError::CannotReadFromFile)
-    }
+/// Show an error in the error popup instead of tearing down the whole TUI
+fn show_error(error: &LocalError) {
+    modal::open(Modal::Error(error.to_string()));
 }
 
-fn remove_selected_task_from_list(list_state: &mut ListState) -> Result<(), LocalError> {
-    if let Some(selected_item) = list_state.selected() {
-        let mut list = read_database().expect("Cannot read DB");
-
-        if list.is_empty() {
-            return Ok(());
-        }
-
-        list.remove(selected_item);
+/// Show a short message in the status bar, it fades out after a few seconds
+fn show_status(message: String) {
+    *STATUS_MESSAGE.lock().unwrap() = Some(StatusMessage {
+        text: message,
+        shown: Instant::now(),
+    });
+}
 
-        write_database(&list);
+/// Drop the status message once it has been shown long enough, called on every tick
+fn expire_status() {
+    let mut status = STATUS_MESSAGE.lock().unwrap();
 
-        if selected_item == 0 {
-            list_state.select(Some(0));
-        } else {
-            list_state.select(Some(selected_item - 1));
+    if let Some(message) = status.as_ref() {
+        if message.shown.elapsed() >= STATUS_TIMEOUT {
+            *status = None;
         }
     }
-
-    Ok(())
 }
 
-fn read_database() -> Result<Vec<Task>, LocalError> {
-    let raw_content = fs::read_to_string(DB_PATH).expect("Cannot read DB");
-
-    let parsed_data: Vec<Task> = serde_json::from_str(&raw_content).expect("Cannot parse data!!");
+/// Keys that apply in the current tab and mode, shown in the footer
+fn footer_hints(app: &App) -> String {
+    let is_task_tab = matches!(
+        app.active_menu_item,
+        MenuItem::Todo | MenuItem::Done | MenuItem::All
+    );
+
+    let bound = |(keys, action): &(Vec<KeyChord>, Action)| format_hint(keys, *action);
+    let mode = |layer: Layer| app.keymap.layer_help(layer).iter().map(bound).collect();
+
+    let hints: Vec<String> = match modal::top() {
+        Some(Modal::NoteForm) if app.note_form.focus == NoteField::Kind => {
+            let mut hints: Vec<String> = mode(Layer::NoteKind);
+            hints.extend(mode(Layer::Form));
+            hints
+        }
+        Some(Modal::TaskForm | Modal::NoteForm) => mode(Layer::Form),
+        Some(Modal::Confirm { .. }) => mode(Layer::Confirm),
+        Some(Modal::Export) => mode(Layer::Export),
+        Some(Modal::Error(_) | Modal::Help) => vec!["any key: close".to_string()],
+        None if is_task_tab && app.view.searching => {
+            let mut hints = vec!["type to search".to_string()];
+            hints.extend(mode(Layer::Search));
+            hints
+        }
+        None if is_task_tab && app.checklist_focus.is_some() => mode(Layer::Checklist),
+        // help comes first so it stays visible when the rest does not fit
+        None => {
+            let help = app.keymap.tab_help(app.active_menu_item);
+            let (first, rest): (Vec<_>, Vec<_>) =
+                help.iter().partition(|(_, action)| *action == Action::Help);
+
+            first.into_iter().chain(rest).map(bound).collect()
+        }
+    };
 
-    Ok(parsed_data)
+    hints.join("  ")
 }
 
-fn write_database(x: &Vec<Task>) {
-    fs::write(DB_PATH, &serde_json::to_vec(x).unwrap()).expect("Cannot Write DB");
-}
 /// Create a vector of all menu titles
 fn create_menu_vector() -> Vec<String> {
     let menu_title: Vec<_> = MenuItem::iter().collect::<Vec<_>>();
 
-    println!("menu_titles : {:?}", menu_title);
-
     let mut vec: Vec<String> = Vec::new();
 
     for item in &menu_title {
@@ -300,14 +528,53 @@ fn create_menu_vector() -> Vec<String> {
 
     vec
 }
-#[derive(Serialize, Deserialize, Clone)]
-struct Task {
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct Task {
     id: usize,
     name: String,
     description: String,
     complete: bool,
     priority: usize,
     created: DateTime<Local>,
+    #[serde(default)]
+    completed: Option<DateTime<Local>>,
+    #[serde(default)]
+    due: Option<DateTime<Local>>,
+    /// `#project` and `@context` tags, see `tags::parse_tags`
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    checklist: Vec<ChecklistItem>,
+}
+
+/// One step of the checklist inside a task
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct ChecklistItem {
+    text: String,
+    done: bool,
+}
+
+impl Task {
+    /// Whether the task belongs in the list of the given tab
+    fn is_shown_on(&self, tab: MenuItem) -> bool {
+        match tab {
+            MenuItem::Todo => !self.complete,
+            MenuItem::Done => self.complete,
+            MenuItem::All => true,
+            _ => false,
+        }
+    }
+
+    /// Done and total number of checklist items, None when the task has no checklist
+    fn checklist_progress(&self) -> Option<(usize, usize)> {
+        if self.checklist.is_empty() {
+            return None;
+        }
+
+        let done = self.checklist.iter().filter(|item| item.done).count();
+
+        Some((done, self.checklist.len()))
+    }
 }
 
 impl Default for Task {
@@ -319,6 +586,89 @@ impl Default for Task {
             complete: false,
             priority: 0,
             created: DateTime::default(),
+            completed: None,
+            due: None,
+            tags: Vec::new(),
+            checklist: Vec::new(),
+        }
+    }
+}
+
+/// Everything the render loop and the input handling share
+pub struct App {
+    pub store: TaskStore,
+    pub active_menu_item: MenuItem,
+    /// init list states here for stateful widgets
+    pub list_states: TaskListStates,
+    /// state of the add / edit task popup form
+    pub task_form: TaskForm,
+    /// sorting and filtering of the task lists
+    pub view: ListView,
+    /// Selected checklist item while focus is in the detail pane, None while it is in the list
+    pub checklist_focus: Option<usize>,
+    /// selected entry of the Home overview
+    pub home_list_state: ListState,
+    pub note_list_state: ListState,
+    /// state of the add / edit note popup form
+    pub note_form: NoteForm,
+    /// how many lines the selected note is scrolled down
+    pub note_scroll: u16,
+    /// which key does what, per tab
+    pub keymap: Keymap,
+    pub themes: Themes,
+}
+
+impl App {
+    fn new(store: TaskStore, keymap: Keymap, themes: Themes) -> Self {
+        Self {
+            store,
+            active_menu_item: MenuItem::Home,
+            list_states: TaskListStates::default(),
+            task_form: TaskForm::default(),
+            view: ListView::default(),
+            checklist_focus: None,
+            home_list_state: ListState::default(),
+            note_list_state: {
+                let mut state = ListState::default();
+                state.select(Some(0));
+                state
+            },
+            note_form: NoteForm::default(),
+            note_scroll: 0,
+            keymap,
+            themes,
+        }
+    }
+}
+
+/// One list state per task tab so each tab keeps its own selection
+pub struct TaskListStates {
+    todo: ListState,
+    done: ListState,
+    all: ListState,
+}
+
+impl Default for TaskListStates {
+    fn default() -> Self {
+        let mut todo = ListState::default();
+        todo.select(Some(0));
+        let mut done = ListState::default();
+        done.select(Some(0));
+        let mut all = ListState::default();
+        all.select(Some(0));
+
+        Self { todo, done, all }
+    }
+}
+
+impl TaskListStates {
+    /// List state of a task tab, None for tabs that do not show tasks
+    pub fn for_tab(&mut self, tab: MenuItem) -> Option<&mut ListState> {
+        match tab {
+            MenuItem::Todo => Some(&mut self.todo),
+            MenuItem::Done => Some(&mut self.done),
+            MenuItem::All => Some(&mut self.all),
+            _ => None,
         }
     }
 }
@@ -328,12 +678,34 @@ pub enum Event<I> {
     Tick,
 }
 
-enum _NoteType<'a> {
-    Short(&'a str),
-    Long(&'a str),
+/// Short notes are quick jottings, long ones are documents that get scrolled through
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Display)]
+pub enum NoteKind {
+    Short,
+    Long,
+}
+
+impl NoteKind {
+    fn toggle(&mut self) {
+        match self {
+            NoteKind::Short => *self = NoteKind::Long,
+            NoteKind::Long => *self = NoteKind::Short,
+        }
+    }
+}
+
+/// A free form note shown on the Notes tab, stored next to the tasks and sharing their IDs
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct Note {
+    id: usize,
+    title: String,
+    body: String,
+    kind: NoteKind,
+    created: DateTime<Local>,
+    modified: DateTime<Local>,
 }
 
-#[derive(Clone, Copy, Debug, EnumIter, Display)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter, Display)]
 pub enum MenuItem {
     Home,
     Todo,
@@ -380,9 +752,81 @@ impl From<MenuItem> for usize {
     }
 }
 
-// TODO use this error stuff for some reason....
-#[derive(Debug)]
+/// Everything that can go wrong while reading or writing the database
+#[derive(Debug, Error)]
 pub enum LocalError {
-    Generic,
-    CannotReadFromFile,
+    #[error("database file {0} does not exist")]
+    DatabaseMissing(String),
+    #[error("cannot parse {path} at line {line}, column {column}: {message}")]
+    Parse {
+        path: String,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    #[error("{path} is not a valid database after upgrading it from version {version}: {message}")]
+    InvalidAfterMigration {
+        path: String,
+        version: u64,
+        message: String,
+    },
+    #[error("permission denied for {0}")]
+    PermissionDenied(String),
+    #[error("cannot read {path}: {source}")]
+    Read { path: String, source: io::Error },
+    #[error("cannot write {path}: {source}")]
+    Write { path: String, source: io::Error },
+    #[error("no task with ID {0}")]
+    TaskNotFound(usize),
+    #[error("no note with ID {0}")]
+    NoteNotFound(usize),
+    #[error("{0} was changed by another toodles, unsaved changes were merged into it")]
+    ChangedOnDisk(String),
+    #[error("the database could not be loaded, changes are not saved")]
+    NotLoaded,
+    #[error("there is no backup that can be restored")]
+    NoBackup,
+    #[error("{path} has no schema version, it does not look like a toodles database")]
+    UnknownVersion { path: String },
+    #[error(
+        "{path} uses schema version {version}, this toodles only understands up to {}",
+        schema::CURRENT_VERSION
+    )]
+    UnsupportedVersion { path: String, version: u64 },
+}
+
+impl LocalError {
+    fn from_read(path: &Path, source: io::Error) -> Self {
+        match source.kind() {
+            io::ErrorKind::NotFound => LocalError::DatabaseMissing(path.display().to_string()),
+            io::ErrorKind::PermissionDenied => {
+                LocalError::PermissionDenied(path.display().to_string())
+            }
+            _ => LocalError::Read {
+                path: path.display().to_string(),
+                source,
+            },
+        }
+    }
+
+    fn from_write(path: &Path, source: io::Error) -> Self {
+        match source.kind() {
+            io::ErrorKind::PermissionDenied => {
+                LocalError::PermissionDenied(path.display().to_string())
+            }
+            _ => LocalError::Write {
+                path: path.display().to_string(),
+                source,
+            },
+        }
+    }
+
+    fn from_parse(path: &Path, source: serde_json::Error) -> Self {
+        LocalError::Parse {
+            path: path.display().to_string(),
+            line: source.line(),
+            column: source.column(),
+            message: source.to_string(),
+        }
+    }
 }
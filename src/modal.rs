@@ -0,0 +1,56 @@
+use std::sync::Mutex;
+
+/// Popups that can be stacked over the main screen, the last one opened gets the keys
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Modal {
+    /// Add / edit task form, its state lives in `App::task_form`
+    TaskForm,
+    /// Add / edit note form, its state lives in `App::note_form`
+    NoteForm,
+    /// Asks before doing something that is hard to take back
+    Confirm {
+        message: String,
+        action: ConfirmAction,
+    },
+    /// Asks for the format to export the current task list in
+    Export,
+    /// Closed by any key
+    Error(String),
+    /// Every key binding, full screen, closed by any key
+    Help,
+}
+
+/// What happens when a confirmation is answered with yes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfirmAction {
+    DeleteTask(usize),
+    DeleteNote(usize),
+}
+
+/// Open popups, bottom to top
+static MODALS: Mutex<Vec<Modal>> = Mutex::new(Vec::new());
+
+/// Put a popup on top. The same popup twice in a row is only shown once, so an error that keeps
+/// happening does not pile up popups faster than they can be closed.
+pub fn open(modal: Modal) {
+    let mut modals = MODALS.lock().unwrap();
+
+    if modals.last() != Some(&modal) {
+        modals.push(modal);
+    }
+}
+
+/// Close the topmost popup
+pub fn close() {
+    MODALS.lock().unwrap().pop();
+}
+
+/// The popup that currently receives the keys
+pub fn top() -> Option<Modal> {
+    MODALS.lock().unwrap().last().cloned()
+}
+
+/// All open popups bottom to top, in the order they are drawn
+pub fn stack() -> Vec<Modal> {
+    MODALS.lock().unwrap().clone()
+}
@@ -1,18 +1,37 @@
 use std::io::Stdout;
 
-use rand::Rng;
+use chrono::prelude::Local;
+use strum::IntoEnumIterator;
 use tui::{
     backend::CrosstermBackend,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
+    style::{Modifier, Style},
     text::{Span, Spans},
     widgets::{
-        Block, BorderType, Borders, Cell, List, ListItem, ListState, Paragraph, Row, Table, Wrap,
+        BarChart, Block, BorderType, Borders, Cell, Clear, List, ListItem, ListState, Paragraph,
+        Row, Sparkline, Table, Wrap,
     },
     Frame,
 };
 
-use crate::{read_database, Task};
+use crate::{
+    checklist::checkbox,
+    dashboard::{
+        completed_since, completions_per_day, home_entries, open_by_priority, EntryKind,
+        COMPLETION_DAYS,
+    },
+    due::{due_state, DueState, DUE_FORMAT},
+    form::{FormField, NoteField, NoteForm, TaskForm},
+    keymap::{format_hint, format_keys, Action, KeyChord, Keymap, Layer},
+    search::name_match,
+    store::TaskStore,
+    theme::Theme,
+    view::ListView,
+    MenuItem, Note, NoteKind, StatusMessage, Task,
+};
+
+/// How timestamps are shown in the detail table
+const DATE_FORMAT: &str = "%Y-%m-%d %H:%M";
 
 pub fn split_main_window(
     frame: &mut Frame<CrosstermBackend<Stdout>>,
@@ -26,6 +45,7 @@ pub fn split_main_window(
             [
                 Constraint::Length(3), // Top
                 Constraint::Min(2),    // Middle Min indicates that it will be dynamic
+                Constraint::Length(1), // Status bar
                 Constraint::Length(3), // Bottom
             ]
             .as_ref(),
@@ -62,25 +82,50 @@ pub fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
         .split(popup_layout[1])[1]
 }
 
-pub fn render_todo_page<'a>(todo_list_state: &ListState) -> (List<'a>, (Table<'a>, Paragraph<'a>)) {
-    // create block
+/// Renders the list / detail layout for one of the task tabs (Todo, Done or All)
+pub fn render_todo_page<'a>(
+    store: &TaskStore,
+    view: &ListView,
+    todo_list_state: &ListState,
+    tab: MenuItem,
+    checklist_focus: Option<usize>,
+    keymap: &Keymap,
+    theme: &Theme,
+) -> (List<'a>, (Table<'a>, Paragraph<'a>)) {
+    // create block, the title shows how the list is sorted and filtered
     let todo_block = Block::default()
         .borders(Borders::ALL)
-        .style(Style::default().fg(Color::White))
-        .title("Todo")
+        .style(theme.text())
+        .title(format!("{} ({})", tab, view.describe()))
         .border_type(BorderType::Plain);
 
-    // fetch items from DB
-    let todo_list = read_database().expect("Cannot read database!!");
+    // fetch items from the store
+    let todo_list = store.tasks_for_tab(tab, view);
+
+    let now = Local::now();
 
     // create list item that will have name of the individual items...
     let items: Vec<_> = todo_list
         .iter()
         .map(|entry| {
-            ListItem::new(Spans::from(vec![Span::styled(
-                entry.name.clone(),
-                Style::default(),
-            )]))
+            let style = if entry.complete {
+                theme.completed()
+            } else {
+                match entry.due.map(|due| due_state(due, now)) {
+                    Some(DueState::Overdue) => Style::default().fg(theme.overdue),
+                    Some(DueState::DueToday) => Style::default().fg(theme.due_today),
+                    _ => theme.text(),
+                }
+            };
+
+            let mut row = highlight_matches(&entry.name, &view.search, style, theme);
+
+            if let Some((done, total)) = entry.checklist_progress() {
+                row.0
+                    .push(Span::styled(format!(" {}/{}", done, total), theme.muted()));
+            }
+
+            ListItem::new(row)
         })
         .collect();
 
@@ -89,21 +134,15 @@ pub fn render_todo_page<'a>(todo_list_state: &ListState) -> (List<'a>, (Table<'a
     let default_task = Task::default();
 
     let selected_todo = todo_list
-        .get(
-            todo_list_state
-                .selected()
-                .expect("Should not error out as there is always something selected... "),
-        )
+        .get(todo_list_state.selected().unwrap_or(0))
+        .copied()
         .unwrap_or(&default_task);
 
     // create the list of todo entries
 
-    let list = List::new(items).block(todo_block).highlight_style(
-        Style::default()
-            .bg(Color::Yellow)
-            .fg(Color::Black)
-            .add_modifier(Modifier::BOLD),
-    );
+    let list = List::new(items)
+        .block(todo_block)
+        .highlight_style(theme.highlight());
 
     // render a table for the currently selected item
     let todo_detail = Table::new(vec![
@@ -113,8 +152,26 @@ pub fn render_todo_page<'a>(todo_list_state: &ListState) -> (List<'a>, (Table<'a
             Cell::from(Span::raw(selected_todo.id.to_string())),
             Cell::from(Span::raw(selected_todo.name.clone())),
             Cell::from(Span::raw(selected_todo.complete.to_string())),
-            Cell::from(Span::raw(selected_todo.priority.to_string())),
-            Cell::from(Span::raw(selected_todo.created.to_string())),
+            Cell::from(Span::styled(
+                selected_todo.priority.to_string(),
+                theme.priority(selected_todo.priority),
+            )),
+            Cell::from(Span::raw(
+                selected_todo.created.format(DATE_FORMAT).to_string(),
+            )),
+            Cell::from(Span::raw(
+                selected_todo
+                    .due
+                    .map(|due| due.format(DUE_FORMAT).to_string())
+                    .unwrap_or_default(),
+            )),
+            Cell::from(Span::raw(
+                selected_todo
+                    .completed
+                    .map(|completed| completed.format(DATE_FORMAT).to_string())
+                    .unwrap_or_default(),
+            )),
+            Cell::from(tag_chips(&selected_todo.tags, theme)),
         ]),
     ])
     .header(Row::new(vec![
@@ -138,81 +195,984 @@ pub fn render_todo_page<'a>(todo_list_state: &ListState) -> (List<'a>, (Table<'a
             "Created",
             Style::default().add_modifier(Modifier::BOLD),
         )),
+        Cell::from(Span::styled(
+            "Due",
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Cell::from(Span::styled(
+            "Completed",
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Cell::from(Span::styled(
+            "Tags",
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
     ]))
     .block(
         Block::default()
             .borders(Borders::ALL)
             .title("Detail")
-            .style(Style::default().fg(Color::White))
+            .style(theme.text())
             .border_type(BorderType::Plain),
     )
     .widths(&[
-        Constraint::Percentage(20),
-        Constraint::Percentage(30),
-        Constraint::Percentage(10),
-        Constraint::Percentage(10),
-        Constraint::Percentage(30),
+        Constraint::Percentage(4),
+        Constraint::Percentage(18),
+        Constraint::Percentage(8),
+        Constraint::Percentage(6),
+        Constraint::Percentage(14),
+        Constraint::Percentage(14),
+        Constraint::Percentage(14),
+        Constraint::Percentage(22),
     ]);
 
-    let todo_desc = Paragraph::new(selected_todo.description.clone()).wrap(Wrap { trim: true });
+    let todo_desc = render_description(selected_todo, checklist_focus, keymap, tab, theme);
 
     (list, (todo_detail, todo_desc))
     // return both widgets to be rendered....
 }
 
-pub fn render_static_test_page<'a>() -> Paragraph<'a> {
-    let mut rng = rand::thread_rng();
+/// Description of a task followed by its checklist. While `checklist_focus` is set the pane has
+/// focus and the item at that index is highlighted.
+fn render_description<'a>(
+    task: &Task,
+    checklist_focus: Option<usize>,
+    keymap: &Keymap,
+    tab: MenuItem,
+    theme: &Theme,
+) -> Paragraph<'a> {
+    let mut lines: Vec<Spans> = task
+        .description
+        .lines()
+        .map(|line| Spans::from(line.to_string()))
+        .collect();
+
+    if !task.checklist.is_empty() {
+        if !lines.is_empty() {
+            lines.push(Spans::from(""));
+        }
+
+        lines.extend(task.checklist.iter().enumerate().map(|(index, item)| {
+            let mut style = if item.done {
+                theme.completed()
+            } else {
+                theme.text()
+            };
+
+            if checklist_focus == Some(index) {
+                style = style.bg(theme.highlight_bg).fg(theme.highlight_fg);
+            }
 
-    let r = rng.gen_range(0, 255);
-    let g = rng.gen_range(0, 255);
-    let b = rng.gen_range(0, 255);
+            Spans::from(Span::styled(
+                format!("{} {}", checkbox(item.done), item.text),
+                style,
+            ))
+        }));
+    }
+
+    let title = match (task.checklist_progress(), checklist_focus) {
+        (Some((done, total)), Some(_)) => format!(
+            "Checklist {}/{}{}",
+            done,
+            total,
+            in_parens(&layer_hints(
+                keymap,
+                Layer::Checklist,
+                &[Action::ToggleItem, Action::Back],
+                ", "
+            ))
+        ),
+        (Some((done, total)), None) => format!(
+            "Description, checklist {}/{}{}",
+            done,
+            total,
+            in_parens(&format_keys(&keymap.tab_keys(tab, Action::FocusChecklist)))
+        ),
+        (None, _) => "Description".to_string(),
+    };
+
+    Paragraph::new(lines).wrap(Wrap { trim: true }).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(title)
+            .style(theme.border(checklist_focus.is_some()))
+            .border_type(BorderType::Plain),
+    )
+}
 
-    let home = Paragraph::new(vec![
+/// Tags drawn as coloured chips
+fn tag_chips<'a>(tags: &[String], theme: &Theme) -> Spans<'a> {
+    Spans::from(
+        tags.iter()
+            .flat_map(|tag| {
+                [
+                    Span::styled(format!(" {} ", tag), theme.tag_chip(tag)),
+                    Span::raw(" "),
+                ]
+            })
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// Hints for some actions of a layer, like `x: toggle item, Esc: back`. Unbound actions are left
+/// out.
+fn layer_hints(keymap: &Keymap, layer: Layer, actions: &[Action], separator: &str) -> String {
+    actions
+        .iter()
+        .map(|action| (keymap.keys(layer, *action), *action))
+        .filter(|(keys, _)| !keys.is_empty())
+        .map(|(keys, action)| format_hint(&keys, action))
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+/// All bindings of a layer as one line of hints
+fn all_layer_hints(keymap: &Keymap, layer: Layer) -> String {
+    keymap
+        .layer_help(layer)
+        .iter()
+        .map(|(keys, action)| format_hint(keys, *action))
+        .collect::<Vec<_>>()
+        .join("  ")
+}
+
+/// Hint for an action on a tab, like `Enter: open`, empty when no key is bound to it
+fn tab_hint(keymap: &Keymap, tab: MenuItem, action: Action) -> String {
+    let keys = keymap.tab_keys(tab, action);
+
+    if keys.is_empty() {
+        String::new()
+    } else {
+        format_hint(&keys, action)
+    }
+}
+
+/// ` (hint)` for a title, nothing when there is no hint because the key is unbound
+fn in_parens(hint: &str) -> String {
+    if hint.is_empty() {
+        String::new()
+    } else {
+        format!(" ({})", hint)
+    }
+}
+
+/// Sidebar of all tags with their open task counts. The tag the lists are filtered on is
+/// selected in the returned state.
+pub fn render_tag_sidebar<'a>(
+    store: &TaskStore,
+    view: &ListView,
+    keymap: &Keymap,
+    tab: MenuItem,
+    theme: &Theme,
+) -> (List<'a>, ListState) {
+    let tags = store.tag_counts();
+
+    let items: Vec<_> = tags
+        .iter()
+        .map(|(tag, open)| {
+            ListItem::new(Spans::from(vec![
+                Span::styled(tag.clone(), Style::default().fg(theme.tag(tag))),
+                Span::styled(format!(" {}", open), theme.muted()),
+            ]))
+        })
+        .collect();
+
+    let mut state = ListState::default();
+    state.select(
+        view.tag
+            .as_ref()
+            .and_then(|current| tags.iter().position(|(tag, _)| tag == current)),
+    );
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(theme.text())
+                .title(format!(
+                    "Tags{}",
+                    in_parens(&format_keys(&keymap.tab_keys(tab, Action::NextTag)))
+                ))
+                .border_type(BorderType::Plain),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+    (list, state)
+}
+
+/// Split a task name into spans so the characters matched by the search stand out
+fn highlight_matches<'a>(name: &str, query: &str, style: Style, theme: &Theme) -> Spans<'a> {
+    let positions = match name_match(query, name) {
+        Some(found) if !found.positions.is_empty() => found.positions,
+        _ => return Spans::from(vec![Span::styled(name.to_string(), style)]),
+    };
+
+    let matched_style = style.fg(theme.accent).add_modifier(Modifier::BOLD);
+
+    Spans::from(
+        name.chars()
+            .enumerate()
+            .map(|(index, c)| {
+                let style = if positions.contains(&index) {
+                    matched_style
+                } else {
+                    style
+                };
+                Span::styled(c.to_string(), style)
+            })
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// Renders the Notes tab: the list of notes on the left and the selected note on the right,
+/// scrolled down by `scroll` lines. The scroll is clamped so the end of the note stays at the
+/// bottom of `detail_area`, where the note is drawn.
+pub fn render_notes_page<'a>(
+    store: &TaskStore,
+    list_state: &ListState,
+    scroll: &mut u16,
+    detail_area: Rect,
+    keymap: &Keymap,
+    theme: &Theme,
+) -> (List<'a>, Paragraph<'a>) {
+    let notes = store.notes();
+
+    let items: Vec<_> = notes
+        .iter()
+        .map(|note| {
+            ListItem::new(Spans::from(vec![
+                Span::styled(format!("[{}] ", note_kind_tag(note.kind)), theme.muted()),
+                Span::raw(note.title.clone()),
+            ]))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(theme.text())
+                .title(format!("Notes ({})", notes.len()))
+                .border_type(BorderType::Plain),
+        )
+        .highlight_style(theme.highlight());
+
+    let selected = list_state.selected().and_then(|index| notes.get(index));
+
+    let (title, mut lines) = match selected {
+        Some(note) => (note.title.clone(), note_header(note, theme)),
+        None => (
+            "No notes".to_string(),
+            vec![Spans::from(Span::styled(
+                press_to(
+                    &keymap.tab_keys(MenuItem::Notes, Action::AddNote),
+                    "write the first note",
+                ),
+                theme.muted(),
+            ))],
+        ),
+    };
+
+    if let Some(note) = selected {
+        lines.extend(note.body.lines().map(|line| Spans::from(line.to_string())));
+    }
+
+    // inside the borders
+    let width = detail_area.width.saturating_sub(2);
+    let height = detail_area.height.saturating_sub(2);
+    let wrapped: usize = lines
+        .iter()
+        .map(|line| {
+            let text: String = line.0.iter().map(|span| span.content.as_ref()).collect();
+            wrapped_height(&text, width)
+        })
+        .sum();
+    let max_scroll = u16::try_from(wrapped)
+        .unwrap_or(u16::MAX)
+        .saturating_sub(height);
+    *scroll = (*scroll).min(max_scroll);
+
+    let detail = Paragraph::new(lines)
+        .wrap(Wrap { trim: false })
+        .scroll((*scroll, 0))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(theme.text())
+                .title(title)
+                .border_type(BorderType::Plain),
+        );
+
+    (list, detail)
+}
+
+/// Rows a line takes when it is wrapped at word boundaries into `width` columns, long words are
+/// broken up
+fn wrapped_height(line: &str, width: u16) -> usize {
+    let width = usize::from(width.max(1));
+    let mut rows = 1;
+    let mut used = 0;
+
+    for word in line.split_inclusive(' ') {
+        let mut length = word.chars().count();
+
+        // the trailing space may hang over the edge
+        let fits = used + length.saturating_sub(usize::from(word.ends_with(' '))) <= width;
+        if used > 0 && !fits {
+            rows += 1;
+            used = 0;
+        }
+
+        while length > width {
+            rows += 1;
+            length -= width;
+        }
+        used += length;
+    }
+
+    rows
+}
+
+fn note_kind_tag(kind: NoteKind) -> &'static str {
+    match kind {
+        NoteKind::Short => "S",
+        NoteKind::Long => "L",
+    }
+}
+
+/// Kind and timestamps of a note followed by a blank line before the body
+fn note_header<'a>(note: &Note, theme: &Theme) -> Vec<Spans<'a>> {
+    let style = theme.muted();
+
+    vec![
+        Spans::from(Span::styled(
+            format!(
+                "{} note, created {}, modified {}",
+                note.kind,
+                note.created.format(DATE_FORMAT),
+                note.modified.format(DATE_FORMAT)
+            ),
+            style,
+        )),
+        Spans::from(""),
+    ]
+}
+
+/// `Press a/A to <what>`, or just what there is to do when no key is bound to it
+fn press_to(keys: &[KeyChord], what: &str) -> String {
+    if keys.is_empty() {
+        format!("Bind a key to {}", what)
+    } else {
+        format!("Press {} to {}", format_keys(keys), what)
+    }
+}
+
+/// Shown in place of a page whose data could not be loaded
+pub fn render_error_page<'a>(
+    error: &str,
+    keymap: &Keymap,
+    tab: MenuItem,
+    theme: &Theme,
+) -> Paragraph<'a> {
+    Paragraph::new(vec![
         Spans::from(vec![Span::raw("")]),
         Spans::from(vec![Span::styled(
-            "RANDOM!!",
-            Style::default().fg(Color::Rgb(r, g, b)),
+            "Cannot load tasks",
+            Style::default()
+                .fg(theme.error)
+                .add_modifier(Modifier::BOLD),
         )]),
         Spans::from(vec![Span::raw("")]),
-        Spans::from(vec![Span::raw("to")]),
+        Spans::from(vec![Span::raw(error.to_string())]),
         Spans::from(vec![Span::raw("")]),
         Spans::from(vec![Span::styled(
-            "toodles",
-            Style::default().fg(Color::LightBlue),
+            press_to(
+                &keymap.tab_keys(tab, Action::RestoreBackup),
+                "restore the latest backup",
+            ),
+            theme.muted(),
         )]),
     ])
     .alignment(Alignment::Center)
+    .wrap(Wrap { trim: true })
     .block(
         Block::default()
             .borders(Borders::ALL)
-            .style(Style::default().fg(Color::White))
-            .title("Home")
+            .style(theme.text())
+            .title("Error")
             .border_type(BorderType::Plain),
-    );
-
-    home
+    )
 }
 
-pub fn render_static_home<'a>() -> Paragraph<'a> {
-    let home = Paragraph::new(vec![
-        Spans::from(vec![Span::raw("")]),
-        Spans::from(vec![Span::raw("Welcome")]),
+/// Draws the error popup on top of everything else
+pub fn render_error_popup(
+    frame: &mut Frame<CrosstermBackend<Stdout>>,
+    message: &str,
+    area: Rect,
+    theme: &Theme,
+) {
+    let popup = Paragraph::new(vec![
         Spans::from(vec![Span::raw("")]),
-        Spans::from(vec![Span::raw("to")]),
+        Spans::from(vec![Span::raw(message.to_string())]),
         Spans::from(vec![Span::raw("")]),
         Spans::from(vec![Span::styled(
-            "toodles",
-            Style::default().fg(Color::LightBlue),
+            "Press any key to continue",
+            theme.muted(),
         )]),
     ])
     .alignment(Alignment::Center)
+    .wrap(Wrap { trim: true })
+    .block(
+        Block::default()
+            .title(" Error ")
+            .borders(Borders::ALL)
+            .style(Style::default().fg(theme.error))
+            .border_type(BorderType::Double),
+    );
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(popup, area);
+}
+
+/// Draws a yes / no question on top of everything else
+pub fn render_confirm_popup(
+    frame: &mut Frame<CrosstermBackend<Stdout>>,
+    message: &str,
+    keymap: &Keymap,
+    area: Rect,
+    theme: &Theme,
+) {
+    let popup = Paragraph::new(vec![
+        Spans::from(vec![Span::raw("")]),
+        Spans::from(vec![Span::raw(message.to_string())]),
+        Spans::from(vec![Span::raw("")]),
+        Spans::from(vec![
+            Span::styled(
+                format_keys(&keymap.keys(Layer::Confirm, Action::Yes)),
+                Style::default().fg(theme.success),
+            ),
+            Span::raw(format!(": {}  ", Action::Yes.describe())),
+            Span::styled(
+                format_keys(&keymap.keys(Layer::Confirm, Action::No)),
+                Style::default().fg(theme.error),
+            ),
+            Span::raw(format!(": {}", Action::No.describe())),
+        ]),
+    ])
+    .alignment(Alignment::Center)
+    .wrap(Wrap { trim: true })
     .block(
         Block::default()
+            .title(" Confirm ")
             .borders(Borders::ALL)
-            .style(Style::default().fg(Color::White))
-            .title("Home")
+            .style(theme.border(true))
+            .border_type(BorderType::Double),
+    );
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(popup, area);
+}
+
+/// Draws the choice of export formats, each starting with the keys that pick it
+pub fn render_export_popup(
+    frame: &mut Frame<CrosstermBackend<Stdout>>,
+    keymap: &Keymap,
+    area: Rect,
+    theme: &Theme,
+) {
+    let mut lines = vec![Spans::from(vec![Span::raw("")])];
+
+    lines.extend(
+        keymap
+            .layer_help(Layer::Export)
+            .into_iter()
+            .filter_map(|(keys, action)| Some((keys, action.export_format()?)))
+            .map(|(keys, format)| {
+                Spans::from(vec![
+                    Span::styled(format_keys(&keys), Style::default().fg(theme.focus)),
+                    Span::raw(format!(": {}", format)),
+                ])
+            }),
+    );
+
+    lines.push(Spans::from(vec![Span::raw("")]));
+    lines.push(Spans::from(vec![Span::styled(
+        layer_hints(keymap, Layer::Export, &[Action::Cancel], "  "),
+        theme.muted(),
+    )]));
+
+    let popup = Paragraph::new(lines).alignment(Alignment::Center).block(
+        Block::default()
+            .title(" Export list ")
+            .borders(Borders::ALL)
+            .style(theme.text())
+            .border_type(BorderType::Double),
+    );
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(popup, area);
+}
+
+/// Status bar: the latest status message on the left, dimmed while it fades out, and task counts
+/// on the right. The total is the length of the list on the current tab with its filters.
+pub fn render_status_bar<'a>(
+    message: Option<&StatusMessage>,
+    store: &TaskStore,
+    view: &ListView,
+    tab: MenuItem,
+    theme: &Theme,
+) -> (Paragraph<'a>, Paragraph<'a>) {
+    let message = match message {
+        Some(message) if message.is_fading() => {
+            Paragraph::new(Span::styled(message.text.clone(), theme.muted()))
+        }
+        Some(message) => Paragraph::new(Span::styled(message.text.clone(), theme.text())),
+        None => Paragraph::new(""),
+    };
+
+    if store.load_error().is_some() {
+        return (message, Paragraph::new(""));
+    }
+
+    let counts = store.counts(Local::now());
+    let mut spans = vec![
+        Span::styled(format!("{} open", counts.open), theme.text()),
+        Span::raw("  "),
+        Span::styled(
+            format!("{} done", counts.done),
+            Style::default().fg(theme.completed),
+        ),
+    ];
+
+    if counts.overdue > 0 {
+        spans.push(Span::raw("  "));
+        spans.push(Span::styled(
+            format!("{} overdue", counts.overdue),
+            Style::default().fg(theme.overdue),
+        ));
+    }
+
+    if matches!(tab, MenuItem::Todo | MenuItem::Done | MenuItem::All) {
+        spans.push(Span::styled(
+            format!("  {} shown", store.tasks_for_tab(tab, view).len()),
+            theme.muted(),
+        ));
+    }
+
+    let counts = Paragraph::new(Spans::from(spans)).alignment(Alignment::Right);
+
+    (message, counts)
+}
+
+/// Footer listing the keys of the current tab and mode. Status messages and task counts are
+/// drawn in the status bar above it.
+pub fn render_footer<'a>(hints: String, theme: &Theme) -> Paragraph<'a> {
+    Paragraph::new(hints)
+        .style(Style::default().fg(theme.footer))
+        .alignment(Alignment::Center)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default().fg(theme.footer_border))
+                .title("Keys")
+                .border_type(BorderType::Thick),
+        )
+}
+
+/// Draws every key binding over the whole screen, one section per layer of the keymap so it
+/// always shows the keys that are really in use
+pub fn render_help(
+    frame: &mut Frame<CrosstermBackend<Stdout>>,
+    keymap: &Keymap,
+    area: Rect,
+    theme: &Theme,
+) {
+    let section = |layer: Layer| {
+        let help = keymap.layer_help(layer);
+        let mut lines = vec![Spans::from(Span::styled(
+            layer.describe(),
+            Style::default()
+                .fg(theme.accent)
+                .add_modifier(Modifier::BOLD),
+        ))];
+
+        lines.extend(help.iter().map(|(keys, action)| {
+            Spans::from(vec![
+                Span::styled(
+                    format!("  {:<18}", format_keys(keys)),
+                    Style::default().fg(theme.focus),
+                ),
+                Span::styled(action.describe(), theme.text()),
+            ])
+        }));
+        lines.push(Spans::from(""));
+
+        (!help.is_empty()).then_some(lines)
+    };
+
+    // the shared layers on the left, the ones of single tabs in the middle, modes on the right
+    let (modes, layers): (Vec<Layer>, Vec<Layer>) =
+        Layer::iter().partition(|layer| layer.is_mode());
+    let (shared, tabs): (Vec<Layer>, Vec<Layer>) = layers
+        .into_iter()
+        .partition(|layer| matches!(layer, Layer::Global | Layer::Tasks));
+
+    let block = Block::default()
+        .title(" Keys (any key closes) ")
+        .borders(Borders::ALL)
+        .style(theme.text())
+        .border_type(BorderType::Double);
+
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .margin(1)
+        .constraints(
+            [
+                Constraint::Ratio(1, 3),
+                Constraint::Ratio(1, 3),
+                Constraint::Ratio(1, 3),
+            ]
+            .as_ref(),
+        )
+        .split(area);
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(block, area);
+
+    for (layers, column) in [
+        (shared, columns[0]),
+        (tabs, columns[1]),
+        (modes, columns[2]),
+    ] {
+        let lines: Vec<Spans> = layers.into_iter().filter_map(section).flatten().collect();
+        frame.render_widget(Paragraph::new(lines), column);
+    }
+}
+
+/// Renders the Home tab: counts by status and priority, completions per day and the list of
+/// overdue, due today and recently completed tasks
+pub fn render_home(
+    frame: &mut Frame<CrosstermBackend<Stdout>>,
+    store: &TaskStore,
+    list_state: &mut ListState,
+    keymap: &Keymap,
+    area: Rect,
+    theme: &Theme,
+) {
+    let now = Local::now();
+    let tasks = store.tasks();
+    let counts = store.counts(now);
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(9), Constraint::Min(3)].as_ref())
+        .split(area);
+
+    let top_row = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(
+            [
+                Constraint::Percentage(25),
+                Constraint::Percentage(30),
+                Constraint::Percentage(45),
+            ]
+            .as_ref(),
+        )
+        .split(rows[0]);
+
+    let count_line = |label: &str, count: usize, style: Style| {
+        Spans::from(vec![
+            Span::styled(format!("{:<11}", label), theme.muted()),
+            Span::styled(count.to_string(), style),
+        ])
+    };
+
+    let status = Paragraph::new(vec![
+        count_line("Open", counts.open, theme.text()),
+        count_line("Done", counts.done, Style::default().fg(theme.completed)),
+        count_line(
+            "Overdue",
+            counts.overdue,
+            Style::default().fg(theme.overdue),
+        ),
+        count_line(
+            "Due today",
+            counts.due_today,
+            Style::default().fg(theme.due_today),
+        ),
+        count_line("Notes", store.notes().len(), theme.text()),
+    ])
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(theme.text())
+            .title("toodles")
             .border_type(BorderType::Plain),
     );
 
-    home
+    let priorities = open_by_priority(tasks);
+    let by_priority = BarChart::default()
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(theme.text())
+                .title("Open by priority")
+                .border_type(BorderType::Plain),
+        )
+        .data(&priorities)
+        .bar_width(6)
+        .bar_gap(2)
+        .bar_style(Style::default().fg(theme.accent))
+        .value_style(theme.highlight())
+        .label_style(theme.muted());
+
+    let per_day = completions_per_day(tasks, now, COMPLETION_DAYS);
+    let completions = Sparkline::default()
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(theme.text())
+                .title(format!(
+                    "Completed per day, last {} days ({})",
+                    COMPLETION_DAYS,
+                    completed_since(tasks, now, COMPLETION_DAYS)
+                ))
+                .border_type(BorderType::Plain),
+        )
+        .data(&per_day)
+        .style(Style::default().fg(theme.success));
+
+    let entries = home_entries(tasks, now);
+    let items: Vec<_> = entries
+        .iter()
+        .filter_map(|entry| Some((entry.kind, store.find(entry.id)?)))
+        .map(|(kind, task)| {
+            let (label, style, when) = match kind {
+                EntryKind::Overdue => ("overdue", Style::default().fg(theme.overdue), task.due),
+                EntryKind::DueToday => ("today", Style::default().fg(theme.due_today), task.due),
+                EntryKind::Completed => ("done", theme.muted(), task.completed),
+            };
+
+            ListItem::new(Spans::from(vec![
+                Span::styled(format!("{:<9}", label), style),
+                Span::styled(task.name.clone(), theme.text()),
+                Span::styled(
+                    when.map(|date| format!("  {}", date.format(DATE_FORMAT)))
+                        .unwrap_or_default(),
+                    theme.muted(),
+                ),
+            ]))
+        })
+        .collect();
+
+    // the entries change as tasks are done elsewhere, keep the selection on one of them
+    match items.len() {
+        0 => list_state.select(None),
+        len => list_state.select(Some(list_state.selected().unwrap_or(0).min(len - 1))),
+    }
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(theme.text())
+                .title(format!(
+                    "Overdue, due today and recently completed{}",
+                    in_parens(&tab_hint(keymap, MenuItem::Home, Action::OpenTask))
+                ))
+                .border_type(BorderType::Plain),
+        )
+        .highlight_style(theme.highlight());
+
+    frame.render_widget(status, top_row[0]);
+    frame.render_widget(by_priority, top_row[1]);
+    frame.render_widget(completions, top_row[2]);
+    frame.render_stateful_widget(list, rows[1], list_state);
+}
+
+/// Draws the add / edit task popup with its fields and places the terminal cursor in the focused one
+pub fn render_task_form(
+    frame: &mut Frame<CrosstermBackend<Stdout>>,
+    form: &TaskForm,
+    keymap: &Keymap,
+    area: Rect,
+    theme: &Theme,
+) {
+    let title = match form.editing {
+        Some(_) => " Edit Todo ",
+        None => " Add New Todo ",
+    };
+
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .style(theme.text())
+        .border_type(BorderType::Double);
+
+    let fields = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints(
+            [
+                Constraint::Length(3), // Name
+                Constraint::Min(3),    // Description
+                Constraint::Length(3), // Priority and due date
+                Constraint::Length(3), // Tags
+                Constraint::Length(1), // Hints
+            ]
+            .as_ref(),
+        )
+        .split(area);
+
+    let text_row = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)].as_ref())
+        .split(fields[1]);
+
+    let bottom_row = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(30), Constraint::Percentage(70)].as_ref())
+        .split(fields[2]);
+
+    let field_block = |title: &'static str, field: FormField| {
+        Block::default()
+            .borders(Borders::ALL)
+            .title(title)
+            .style(theme.border(form.focus == field))
+            .border_type(BorderType::Plain)
+    };
+
+    let name = Paragraph::new(form.name.value.clone()).block(field_block("Name", FormField::Name));
+    let description = Paragraph::new(form.description.value.clone())
+        .block(field_block("Description", FormField::Description));
+    let checklist = Paragraph::new(form.checklist.value.clone()).block(field_block(
+        "Checklist (one per line, [x] = done)",
+        FormField::Checklist,
+    ));
+    let priority = Paragraph::new(form.priority.value.clone())
+        .block(field_block("Priority", FormField::Priority));
+    let due = Paragraph::new(form.due.value.clone()).block(field_block(
+        "Due (today, tomorrow, +3d, fri, 2024-05-01 [14:30])",
+        FormField::Due,
+    ));
+    let tags = Paragraph::new(form.tags.value.clone()).block(field_block(
+        "Tags (#project @context, separated by spaces)",
+        FormField::Tags,
+    ));
+    let hints = match &form.error {
+        Some(error) => Paragraph::new(error.clone()).style(Style::default().fg(theme.error)),
+        None => Paragraph::new(all_layer_hints(keymap, Layer::Form)).style(theme.muted()),
+    }
+    .alignment(Alignment::Center);
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(block, area);
+    frame.render_widget(name, fields[0]);
+    frame.render_widget(description, text_row[0]);
+    frame.render_widget(checklist, text_row[1]);
+    frame.render_widget(priority, bottom_row[0]);
+    frame.render_widget(due, bottom_row[1]);
+    frame.render_widget(tags, fields[3]);
+    frame.render_widget(hints, fields[4]);
+
+    // put the cursor inside the focused field, offset by one for the border
+    let (field, field_area) = match form.focus {
+        FormField::Name => (&form.name, fields[0]),
+        FormField::Description => (&form.description, text_row[0]),
+        FormField::Checklist => (&form.checklist, text_row[1]),
+        FormField::Priority => (&form.priority, bottom_row[0]),
+        FormField::Due => (&form.due, bottom_row[1]),
+        FormField::Tags => (&form.tags, fields[3]),
+    };
+    let (column, line) = field.cursor_position();
+
+    frame.set_cursor(
+        (field_area.x + 1 + column).min(field_area.right().saturating_sub(2)),
+        (field_area.y + 1 + line).min(field_area.bottom().saturating_sub(2)),
+    );
+}
+
+/// Draws the add / edit note popup, works like the task form
+pub fn render_note_form(
+    frame: &mut Frame<CrosstermBackend<Stdout>>,
+    form: &NoteForm,
+    keymap: &Keymap,
+    area: Rect,
+    theme: &Theme,
+) {
+    let title = match form.editing {
+        Some(_) => " Edit Note ",
+        None => " Add New Note ",
+    };
+
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .style(theme.text())
+        .border_type(BorderType::Double);
+
+    let fields = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints(
+            [
+                Constraint::Length(3), // Title and kind
+                Constraint::Min(3),    // Body
+                Constraint::Length(1), // Hints
+            ]
+            .as_ref(),
+        )
+        .split(area);
+
+    let top_row = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(80), Constraint::Percentage(20)].as_ref())
+        .split(fields[0]);
+
+    let field_block = |title: &'static str, field: NoteField| {
+        Block::default()
+            .borders(Borders::ALL)
+            .title(title)
+            .style(theme.border(form.focus == field))
+            .border_type(BorderType::Plain)
+    };
+
+    let note_title =
+        Paragraph::new(form.title.value.clone()).block(field_block("Title", NoteField::Title));
+    let kind_title = format!(
+        "Kind{}",
+        in_parens(&format_keys(
+            &keymap.keys(Layer::NoteKind, Action::ToggleKind)
+        ))
+    );
+    let kind = Paragraph::new(form.kind.to_string())
+        .block(field_block("Kind", NoteField::Kind).title(kind_title));
+    let body = Paragraph::new(form.body.value.clone())
+        .wrap(Wrap { trim: false })
+        .block(field_block("Body", NoteField::Body));
+    let hints = match &form.error {
+        Some(error) => Paragraph::new(error.clone()).style(Style::default().fg(theme.error)),
+        None => Paragraph::new(all_layer_hints(keymap, Layer::Form)).style(theme.muted()),
+    }
+    .alignment(Alignment::Center);
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(block, area);
+    frame.render_widget(note_title, top_row[0]);
+    frame.render_widget(kind, top_row[1]);
+    frame.render_widget(body, fields[1]);
+    frame.render_widget(hints, fields[2]);
+
+    // the kind is toggled rather than typed, so it gets no cursor
+    let (field, field_area) = match form.focus {
+        NoteField::Title => (&form.title, top_row[0]),
+        NoteField::Body => (&form.body, fields[1]),
+        NoteField::Kind => return,
+    };
+    let (column, line) = field.cursor_position();
+
+    frame.set_cursor(
+        (field_area.x + 1 + column).min(field_area.right().saturating_sub(2)),
+        (field_area.y + 1 + line).min(field_area.bottom().saturating_sub(2)),
+    );
 }
@@ -0,0 +1,284 @@
+use std::path::Path;
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{LocalError, Note, Task};
+
+/// Version written into every database file, bump it together with a new step in `MIGRATIONS`
+pub const CURRENT_VERSION: u64 = 5;
+
+/// A single upgrade step, turns a database of version `n` into one of version `n + 1`
+type Migration = fn(Value) -> Value;
+
+/// Upgrade steps, the step at index `n` upgrades version `n` to version `n + 1`
+const MIGRATIONS: [Migration; CURRENT_VERSION as usize] =
+    [v0_to_v1, v1_to_v2, v2_to_v3, v3_to_v4, v4_to_v5];
+
+/// Version 0 is the original format: a bare array of tasks, some written before tasks had a
+/// `completed` timestamp. Version 1 wraps the tasks in an envelope that carries the version.
+fn v0_to_v1(value: Value) -> Value {
+    let mut tasks = value;
+
+    if let Some(tasks) = tasks.as_array_mut() {
+        for task in tasks.iter_mut().filter_map(Value::as_object_mut) {
+            task.entry("completed").or_insert(Value::Null);
+        }
+    }
+
+    json!({ "version": 1, "tasks": tasks })
+}
+
+/// Version 2 adds an optional `due` date to every task
+fn v1_to_v2(mut value: Value) -> Value {
+    if let Some(tasks) = value["tasks"].as_array_mut() {
+        for task in tasks.iter_mut().filter_map(Value::as_object_mut) {
+            task.entry("due").or_insert(Value::Null);
+        }
+    }
+
+    value["version"] = json!(2);
+    value
+}
+
+/// Version 3 stores notes next to the tasks
+fn v2_to_v3(mut value: Value) -> Value {
+    value["notes"] = json!([]);
+    value["version"] = json!(3);
+    value
+}
+
+/// Version 4 adds a list of tags to every task
+fn v3_to_v4(mut value: Value) -> Value {
+    if let Some(tasks) = value["tasks"].as_array_mut() {
+        for task in tasks.iter_mut().filter_map(Value::as_object_mut) {
+            task.entry("tags").or_insert(json!([]));
+        }
+    }
+
+    value["version"] = json!(4);
+    value
+}
+
+/// Version 5 adds a checklist to every task
+fn v4_to_v5(mut value: Value) -> Value {
+    if let Some(tasks) = value["tasks"].as_array_mut() {
+        for task in tasks.iter_mut().filter_map(Value::as_object_mut) {
+            task.entry("checklist").or_insert(json!([]));
+        }
+    }
+
+    value["version"] = json!(5);
+    value
+}
+
+/// Version of a raw database, a bare array predates the envelope and counts as version 0
+fn version_of(value: &Value) -> Option<u64> {
+    match value {
+        Value::Array(_) => Some(0),
+        Value::Object(map) => map.get("version").and_then(Value::as_u64),
+        _ => None,
+    }
+}
+
+/// Everything that is kept in the database file
+#[derive(Deserialize)]
+pub struct Database {
+    pub tasks: Vec<Task>,
+    pub notes: Vec<Note>,
+}
+
+/// Parse a database file of any known version, upgrading it to `CURRENT_VERSION`.
+/// Returns the contents and whether a migration happened, so the caller can write the upgrade
+/// back.
+pub fn parse_database(path: &Path, raw_content: &str) -> Result<(Database, bool), LocalError> {
+    let mut value: Value =
+        serde_json::from_str(raw_content).map_err(|e| LocalError::from_parse(path, e))?;
+
+    let version = version_of(&value).ok_or_else(|| LocalError::UnknownVersion {
+        path: path.display().to_string(),
+    })?;
+
+    if version > CURRENT_VERSION {
+        return Err(LocalError::UnsupportedVersion {
+            path: path.display().to_string(),
+            version,
+        });
+    }
+
+    // a current file is read again from the text so errors point at the line they are on
+    if version == CURRENT_VERSION {
+        let database =
+            serde_json::from_str(raw_content).map_err(|e| LocalError::from_parse(path, e))?;
+
+        return Ok((database, false));
+    }
+
+    for migration in &MIGRATIONS[version as usize..] {
+        value = migration(value);
+    }
+
+    // the upgraded value has no lines any more, only the message is left to report
+    let database =
+        serde_json::from_value(value).map_err(|e| LocalError::InvalidAfterMigration {
+            path: path.display().to_string(),
+            version,
+            message: e.to_string(),
+        })?;
+
+    Ok((database, version < CURRENT_VERSION))
+}
+
+/// Serialise the database in the current format
+pub fn serialize_database(tasks: &[Task], notes: &[Note]) -> Vec<u8> {
+    // serialising plain structs with string keys cannot fail
+    serde_json::to_vec(&json!({ "version": CURRENT_VERSION, "tasks": tasks, "notes": notes }))
+        .expect("Database serialises to JSON")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NoteKind;
+
+    fn parse(raw_content: &str) -> Result<(Vec<Task>, bool), LocalError> {
+        parse_database(Path::new("fixture.json"), raw_content)
+            .map(|(database, migrated)| (database.tasks, migrated))
+    }
+
+    #[test]
+    fn v0_bare_array_is_migrated() {
+        let (tasks, migrated) = parse(include_str!("../tests/fixtures/db_v0.json")).unwrap();
+
+        assert!(migrated);
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].id, 64);
+        assert_eq!(tasks[0].name, "NEW DEFAULT NAME");
+        assert!(tasks.iter().all(|task| task.completed.is_none()));
+    }
+
+    #[test]
+    fn v0_with_completion_keeps_timestamp() {
+        let (tasks, migrated) =
+            parse(include_str!("../tests/fixtures/db_v0_completed.json")).unwrap();
+
+        assert!(migrated);
+        assert!(tasks[0].complete);
+        assert!(tasks[0].completed.is_some());
+    }
+
+    #[test]
+    fn v1_is_migrated() {
+        let (tasks, migrated) = parse(include_str!("../tests/fixtures/db_v1.json")).unwrap();
+
+        assert!(migrated);
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[1].priority, 7);
+        assert!(tasks.iter().all(|task| task.due.is_none()));
+    }
+
+    #[test]
+    fn v2_is_migrated() {
+        let raw_content = include_str!("../tests/fixtures/db_v2.json");
+        let (database, migrated) = parse_database(Path::new("v2.json"), raw_content).unwrap();
+
+        assert!(migrated);
+        assert_eq!(database.tasks.len(), 2);
+        assert!(database.tasks[0].due.is_some());
+        assert!(database.tasks[1].due.is_none());
+        assert!(database.notes.is_empty());
+    }
+
+    #[test]
+    fn v3_is_migrated() {
+        let raw_content = include_str!("../tests/fixtures/db_v3.json");
+        let (database, migrated) = parse_database(Path::new("v3.json"), raw_content).unwrap();
+
+        assert!(migrated);
+        assert_eq!(database.tasks.len(), 1);
+        assert!(database.tasks[0].tags.is_empty());
+        assert_eq!(database.notes.len(), 2);
+        assert_eq!(database.notes[1].kind, NoteKind::Long);
+    }
+
+    #[test]
+    fn v4_is_migrated() {
+        let raw_content = include_str!("../tests/fixtures/db_v4.json");
+        let (database, migrated) = parse_database(Path::new("v4.json"), raw_content).unwrap();
+
+        assert!(migrated);
+        assert_eq!(database.tasks[0].tags, vec!["#backend", "@home"]);
+        assert!(database.tasks.iter().all(|task| task.checklist.is_empty()));
+        assert_eq!(database.notes.len(), 1);
+    }
+
+    #[test]
+    fn v5_is_loaded_as_is() {
+        let raw_content = include_str!("../tests/fixtures/db_v5.json");
+        let (database, migrated) = parse_database(Path::new("v5.json"), raw_content).unwrap();
+
+        assert!(!migrated);
+        assert_eq!(database.tasks[0].checklist.len(), 3);
+        assert!(database.tasks[0].checklist[1].done);
+    }
+
+    #[test]
+    fn current_format_round_trips() {
+        let raw_content = include_str!("../tests/fixtures/db_v5.json");
+        let (database, _) = parse_database(Path::new("v5.json"), raw_content).unwrap();
+        let written =
+            String::from_utf8(serialize_database(&database.tasks, &database.notes)).unwrap();
+        let (reread, migrated) = parse_database(Path::new("v5.json"), &written).unwrap();
+
+        assert!(!migrated);
+        assert_eq!(reread.tasks.len(), database.tasks.len());
+        assert_eq!(reread.tasks[0].name, database.tasks[0].name);
+        assert_eq!(reread.tasks[0].tags, database.tasks[0].tags);
+        assert_eq!(
+            reread.tasks[0].checklist[2].text,
+            database.tasks[0].checklist[2].text
+        );
+        assert_eq!(reread.notes[0].body, database.notes[0].body);
+    }
+
+    #[test]
+    fn newer_version_is_rejected() {
+        let result = parse(r#"{"version": 999, "tasks": []}"#);
+
+        assert!(matches!(
+            result,
+            Err(LocalError::UnsupportedVersion { version: 999, .. })
+        ));
+    }
+
+    #[test]
+    fn missing_version_is_rejected() {
+        let result = parse(r#"{"tasks": []}"#);
+
+        assert!(matches!(result, Err(LocalError::UnknownVersion { .. })));
+    }
+
+    #[test]
+    fn broken_json_reports_position() {
+        let result = parse("[\n  {\"id\": 1,\n");
+
+        assert!(matches!(result, Err(LocalError::Parse { line: 3, .. })));
+    }
+
+    #[test]
+    fn invalid_current_file_reports_position() {
+        let result = parse("{\n  \"version\": 5,\n  \"tasks\": 7,\n  \"notes\": []\n}");
+
+        assert!(matches!(result, Err(LocalError::Parse { line: 3, .. })));
+    }
+
+    #[test]
+    fn invalid_migrated_file_reports_no_position() {
+        let result = parse(r#"[{"id": "one"}]"#);
+
+        assert!(matches!(
+            result,
+            Err(LocalError::InvalidAfterMigration { version: 0, .. })
+        ));
+    }
+}
@@ -0,0 +1,162 @@
+use crate::Task;
+
+/// Where a fuzzy query matched inside a piece of text
+#[derive(Debug, PartialEq, Eq)]
+pub struct FuzzyMatch {
+    pub score: i64,
+    /// Char indices of the matched characters, used for highlighting
+    pub positions: Vec<usize>,
+}
+
+/// Match `query` as a case-insensitive subsequence of `text`.
+///
+/// Characters that follow each other directly or start a word score higher, so `nt` ranks
+/// "new task" above "nightly report". Whitespace in the query is ignored.
+pub fn fuzzy_match(query: &str, text: &str) -> Option<FuzzyMatch> {
+    let query: Vec<char> = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect();
+
+    if query.is_empty() {
+        return Some(FuzzyMatch {
+            score: 0,
+            positions: Vec::new(),
+        });
+    }
+
+    let mut positions = Vec::with_capacity(query.len());
+    let mut score = 0;
+    let mut wanted = query.iter().peekable();
+    let mut previous: Option<char> = None;
+
+    for (index, c) in text.chars().enumerate() {
+        let Some(&&next) = wanted.peek() else {
+            break;
+        };
+
+        if c.to_lowercase().eq(std::iter::once(next)) {
+            score += 1;
+
+            if positions
+                .last()
+                .map(|last| last + 1 == index)
+                .unwrap_or(false)
+            {
+                score += 5;
+            }
+
+            if previous.map(|p| !p.is_alphanumeric()).unwrap_or(true) {
+                score += 3;
+            }
+
+            positions.push(index);
+            wanted.next();
+        }
+
+        previous = Some(c);
+    }
+
+    if wanted.peek().is_some() {
+        return None;
+    }
+
+    // prefer matches that are close together
+    let spread = positions.last().unwrap_or(&0) - positions.first().unwrap_or(&0);
+    score -= spread as i64 / 4;
+
+    Some(FuzzyMatch { score, positions })
+}
+
+/// Lowest score a name match needs, below it the matched characters are too scattered to mean
+/// anything
+const MIN_NAME_SCORE: i64 = 0;
+
+/// Score of a task that only matched in its description, below every name match that counts
+const DESCRIPTION_SCORE: i64 = MIN_NAME_SCORE - 1;
+
+/// Fuzzy match of a task name, without the matches that are cut off as too scattered
+pub fn name_match(query: &str, name: &str) -> Option<FuzzyMatch> {
+    fuzzy_match(query, name).filter(|found| found.score >= MIN_NAME_SCORE)
+}
+
+/// How well a task matches the query, `None` when it does not match at all.
+///
+/// The name is matched fuzzily, the description only as a case-insensitive substring since a
+/// subsequence of a long description matches almost any short query.
+pub fn task_score(query: &str, task: &Task) -> Option<i64> {
+    let name = name_match(query, &task.name).map(|found| found.score);
+
+    name.or_else(|| {
+        task.description
+            .to_lowercase()
+            .contains(query.trim().to_lowercase().as_str())
+            .then_some(DESCRIPTION_SCORE)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subsequence_matches_case_insensitive() {
+        let found = fuzzy_match("NwTk", "new task").unwrap();
+
+        assert_eq!(found.positions, vec![0, 2, 4, 7]);
+    }
+
+    #[test]
+    fn missing_characters_do_not_match() {
+        assert_eq!(fuzzy_match("xyz", "new task"), None);
+        assert_eq!(fuzzy_match("ksat", "task"), None);
+    }
+
+    #[test]
+    fn consecutive_and_word_start_matches_score_higher() {
+        let tight = fuzzy_match("nt", "new task").unwrap();
+        let loose = fuzzy_match("nt", "nightly report").unwrap();
+
+        assert!(tight.score > loose.score);
+    }
+
+    #[test]
+    fn empty_query_matches_everything() {
+        assert!(fuzzy_match("", "anything").is_some());
+        assert!(fuzzy_match("  ", "").is_some());
+    }
+
+    fn task(name: &str, description: &str) -> Task {
+        Task {
+            name: name.to_string(),
+            description: description.to_string(),
+            ..Task::default()
+        }
+    }
+
+    #[test]
+    fn descriptions_match_as_substrings_only() {
+        let found = task("groceries", "buy milk and eggs");
+
+        assert!(task_score("milk", &found).is_some());
+        assert!(task_score("MILK", &found).is_some());
+        assert_eq!(task_score("bme", &found), None);
+    }
+
+    #[test]
+    fn name_matches_rank_above_description_matches() {
+        let by_name = task_score("tax", &task("file taxes", "")).unwrap();
+        let by_description = task_score("tax", &task("paperwork", "taxes are due")).unwrap();
+
+        assert!(by_name > by_description);
+    }
+
+    #[test]
+    fn scattered_name_matches_are_cut_off() {
+        let scattered = task("explain the rules of the quiz", "");
+
+        assert_eq!(task_score("ez", &scattered), None);
+        assert!(task_score("quiz", &scattered).is_some());
+    }
+}
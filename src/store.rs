@@ -0,0 +1,926 @@
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap, HashSet},
+    fs::{self, Metadata},
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use chrono::{prelude::Local, DateTime};
+use tui::widgets::ListState;
+
+use crate::{
+    backup::write_atomically,
+    config,
+    due::{due_state, DueState},
+    form::{NoteForm, TaskForm},
+    history::{History, Snapshot},
+    schema::{parse_database, serialize_database, Database},
+    view::ListView,
+    LocalError, MenuItem, Note, Task,
+};
+
+/// How many tasks are in which state, for the status bar
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TaskCounts {
+    pub open: usize,
+    pub done: usize,
+    /// Open tasks past their due date
+    pub overdue: usize,
+    /// Open tasks due later today
+    pub due_today: usize,
+}
+
+/// All tasks and notes, loaded once at startup and changed in memory.
+///
+/// Every change marks the store dirty, the main loop then calls `flush` to write it back to disk.
+///
+/// The CLI can change the database while the UI has it open. The store remembers what the file
+/// looked like when it was last read or written: without pending changes it picks up the new
+/// file with `reload_if_changed`, and with pending changes `flush` merges them into the new file
+/// instead of overwriting it.
+pub struct TaskStore {
+    /// Database file the store was loaded from and is written back to
+    path: PathBuf,
+    /// The file as it was last read or written, `None` when there was no file
+    on_disk: Option<FileStamp>,
+    /// Contents of the file as it was last read or written, what changes made here are
+    /// measured against when the file changed underneath them
+    base: Snapshot,
+    tasks: Vec<Task>,
+    notes: Vec<Note>,
+    /// Shared by tasks and notes so an ID is unique across the whole database
+    next_id: usize,
+    dirty: bool,
+    /// Set when the last write failed. Flushing then waits for the next change instead of
+    /// failing again on every pass of the main loop.
+    write_failed: bool,
+    /// Set when the database could not be loaded, the store then refuses changes so the
+    /// broken file is not overwritten with an empty list
+    load_error: Option<String>,
+    /// Undo / redo for the session, not persisted
+    history: History,
+}
+
+impl TaskStore {
+    /// Load the database chosen at startup
+    pub fn load() -> Result<Self, LocalError> {
+        Self::open(config::db_path())
+    }
+
+    /// Load the database at `path`
+    pub fn open(path: &Path) -> Result<Self, LocalError> {
+        let on_disk = FileStamp::of(path);
+        let (Database { mut tasks, notes }, migrated) = read_database(path)?;
+        let base = Snapshot {
+            tasks: tasks.clone(),
+            notes: notes.clone(),
+        };
+        let mut next_id = read_next_id(path).max(next_free_id(notes.iter().map(|note| note.id)));
+
+        // older databases handed out random IDs which can collide
+        let fixed = fix_duplicate_ids(&mut tasks, &mut next_id);
+
+        // upgrades are written back by the first flush of the main loop
+        Ok(Self {
+            path: path.to_path_buf(),
+            on_disk,
+            base,
+            next_id: next_id.max(next_free_id(tasks.iter().map(|task| task.id))),
+            tasks,
+            notes,
+            dirty: migrated || fixed,
+            write_failed: false,
+            load_error: None,
+            history: History::default(),
+        })
+    }
+
+    /// Empty store used when loading failed, see `load_error`
+    pub fn unloaded(error: &LocalError) -> Self {
+        Self {
+            path: PathBuf::new(),
+            on_disk: None,
+            base: Snapshot::default(),
+            tasks: Vec::new(),
+            notes: Vec::new(),
+            next_id: 0,
+            dirty: false,
+            write_failed: false,
+            load_error: Some(error.to_string()),
+            history: History::default(),
+        }
+    }
+
+    pub fn load_error(&self) -> Option<&str> {
+        self.load_error.as_deref()
+    }
+
+    /// Write the tasks and the ID counter back to disk if anything changed since the last flush.
+    ///
+    /// A failed write is reported once. The store stays dirty, but it is only written again
+    /// after the next change or by `flush_on_exit`.
+    ///
+    /// When another toodles changed the file since it was read, it is not overwritten. The
+    /// pending changes are merged into the file's contents instead, see `merge_from_disk`, which
+    /// is reported as an error. The merged result is written by the next flush.
+    pub fn flush(&mut self) -> Result<(), LocalError> {
+        if !self.dirty || self.write_failed || self.load_error.is_some() {
+            return Ok(());
+        }
+
+        if self.changed_on_disk() {
+            // a file that is gone or unreadable now is reported once, like a failed write
+            if let Err(e) = self.merge_from_disk() {
+                self.write_failed = true;
+                return Err(e);
+            }
+
+            return Err(LocalError::ChangedOnDisk(self.path.display().to_string()));
+        }
+
+        let content = serialize_database(&self.tasks, &self.notes);
+        let written = write_atomically(&self.path, &content)
+            .and_then(|_| write_next_id(&self.path, self.next_id));
+
+        match written {
+            Ok(()) => {
+                self.dirty = false;
+                self.base = self.snapshot();
+                self.on_disk = fs::metadata(&self.path)
+                    .ok()
+                    .map(|metadata| FileStamp::new(&metadata, &content));
+            }
+            Err(_) => self.write_failed = true,
+        }
+
+        written
+    }
+
+    /// Read the file again when another toodles changed it and nothing is waiting to be written.
+    /// Returns whether the store was reloaded.
+    pub fn reload_if_changed(&mut self) -> Result<bool, LocalError> {
+        if self.dirty || self.load_error.is_some() || !self.changed_on_disk() {
+            return Ok(false);
+        }
+
+        self.merge_from_disk()?;
+
+        Ok(true)
+    }
+
+    /// Read the file again and put the changes made here since it was last read or written on
+    /// top of it.
+    ///
+    /// A task or note changed here wins over the file, everything else is taken from the file,
+    /// so the changes of the other toodles and the pending ones both survive. The undo history
+    /// is merged the same way, undoing then leaves the other toodles' changes alone.
+    fn merge_from_disk(&mut self) -> Result<(), LocalError> {
+        // a file that cannot be read is not retried until it changes again
+        self.on_disk = FileStamp::of(&self.path);
+
+        let (Database { tasks, notes }, _) = read_database(&self.path)?;
+        let theirs = Snapshot { tasks, notes };
+
+        // IDs handed out here that the other toodles gave to something else meanwhile
+        let taken: HashSet<usize> = snapshot_ids(&theirs).collect();
+        let synced: HashSet<usize> = snapshot_ids(&self.base).collect();
+        let mut next_id = self
+            .next_id
+            .max(read_next_id(&self.path))
+            .max(next_free_id(taken.iter().copied()));
+        let mut renumbered = HashMap::new();
+
+        let current = self.snapshot();
+        let recorded = self.history.snapshots_mut().map(|snapshot| &*snapshot);
+        for snapshot in [&current].into_iter().chain(recorded) {
+            for id in snapshot_ids(snapshot) {
+                if taken.contains(&id) && !synced.contains(&id) {
+                    renumbered.entry(id).or_insert_with(|| {
+                        next_id += 1;
+                        next_id - 1
+                    });
+                }
+            }
+        }
+
+        let base = std::mem::replace(&mut self.base, theirs);
+        let merge = |snapshot: &Snapshot| {
+            merge_snapshots(&base, &renumber(snapshot, &renumbered), &self.base)
+        };
+
+        for snapshot in self.history.snapshots_mut() {
+            *snapshot = merge(snapshot);
+        }
+        let merged = merge(&current);
+
+        self.next_id = next_id.max(next_free_id(snapshot_ids(&merged)));
+        self.tasks = merged.tasks;
+        self.notes = merged.notes;
+
+        Ok(())
+    }
+
+    /// Whether the file is different from when it was last read or written. It is only read and
+    /// hashed when its size or modification time changed, checking it on every tick stays cheap.
+    fn changed_on_disk(&mut self) -> bool {
+        match (&self.on_disk, fs::metadata(&self.path)) {
+            (Some(stamp), Ok(metadata)) if stamp.matches(&metadata) => return false,
+            (None, Err(_)) => return false,
+            _ => {}
+        }
+
+        let now = FileStamp::of(&self.path);
+
+        // touched or written again with the same content
+        if now.is_some() && now.map(|stamp| stamp.hash) == self.on_disk.map(|stamp| stamp.hash) {
+            self.on_disk = now;
+            return false;
+        }
+
+        true
+    }
+
+    /// Last attempt to write pending changes before quitting, even after a failed write
+    pub fn flush_on_exit(&mut self) -> Result<(), LocalError> {
+        self.write_failed = false;
+
+        match self.flush() {
+            // there is no next flush to write the merged changes
+            Err(LocalError::ChangedOnDisk(_)) => self.flush(),
+            result => result,
+        }
+    }
+
+    /// Note a change that has to be written by the next flush
+    fn mark_dirty(&mut self) {
+        self.dirty = true;
+        self.write_failed = false;
+    }
+
+    /// Only the tasks that are shown on the given tab, sorted and filtered the way they are listed
+    pub fn tasks_for_tab(&self, tab: MenuItem, view: &ListView) -> Vec<&Task> {
+        view.apply(self.tasks.iter().filter(|task| task.is_shown_on(tab)))
+    }
+
+    /// Every task in file order
+    pub fn tasks(&self) -> &[Task] {
+        &self.tasks
+    }
+
+    /// Open, done and overdue tasks across the whole database
+    pub fn counts(&self, now: DateTime<Local>) -> TaskCounts {
+        let mut counts = TaskCounts::default();
+
+        for task in &self.tasks {
+            if task.complete {
+                counts.done += 1;
+            } else {
+                counts.open += 1;
+
+                match task.due.map(|due| due_state(due, now)) {
+                    Some(DueState::Overdue) => counts.overdue += 1,
+                    Some(DueState::DueToday) => counts.due_today += 1,
+                    _ => {}
+                }
+            }
+        }
+
+        counts
+    }
+
+    /// Every tag in use with the number of open tasks carrying it, sorted by name
+    pub fn tag_counts(&self) -> Vec<(String, usize)> {
+        let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+
+        for task in &self.tasks {
+            for tag in &task.tags {
+                *counts.entry(tag).or_default() += usize::from(!task.complete);
+            }
+        }
+
+        counts
+            .into_iter()
+            .map(|(tag, count)| (tag.to_string(), count))
+            .collect()
+    }
+
+    /// Look up a single task by its ID
+    pub fn find(&self, id: usize) -> Option<&Task> {
+        self.tasks.iter().find(|task| task.id == id)
+    }
+
+    /// The task currently selected in the list of the given tab
+    pub fn selected_task(
+        &self,
+        list_state: &ListState,
+        tab: MenuItem,
+        view: &ListView,
+    ) -> Option<&Task> {
+        list_state
+            .selected()
+            .and_then(|selected| self.tasks_for_tab(tab, view).get(selected).copied())
+    }
+
+    pub fn add(&mut self, mut task: Task) -> Result<usize, LocalError> {
+        self.check_writable()?;
+
+        self.record(format!("add '{}'", task.name));
+        task.id = self.allocate_id();
+        let id = task.id;
+        self.tasks.push(task);
+        self.mark_dirty();
+
+        Ok(id)
+    }
+
+    /// Apply the edit form to the task with the matching ID, the list position does not matter
+    pub fn update(&mut self, id: usize, form: &TaskForm) -> Result<bool, LocalError> {
+        self.check_writable()?;
+
+        let before = self.snapshot();
+        let task = self.find_mut(id)?;
+        let name = task.name.clone();
+
+        let updated = form.apply_to(task);
+        if updated {
+            self.history.record(format!("edit '{}'", name), before);
+            self.mark_dirty();
+        }
+
+        Ok(updated)
+    }
+
+    /// Flip the completion state of the task with the given ID and record when it was completed
+    pub fn toggle_complete(&mut self, id: usize) -> Result<(), LocalError> {
+        self.check_writable()?;
+
+        let task = self.find(id).ok_or(LocalError::TaskNotFound(id))?;
+        let description = match task.complete {
+            true => format!("reopen '{}'", task.name),
+            false => format!("complete '{}'", task.name),
+        };
+        self.record(description);
+
+        let task = self.find_mut(id)?;
+        task.complete = !task.complete;
+        task.completed = if task.complete {
+            Some(Local::now())
+        } else {
+            None
+        };
+        self.mark_dirty();
+
+        Ok(())
+    }
+
+    /// Flip the done state of one checklist item of the task with the given ID
+    pub fn toggle_checklist_item(&mut self, id: usize, index: usize) -> Result<(), LocalError> {
+        self.check_writable()?;
+
+        let item = match self
+            .find(id)
+            .ok_or(LocalError::TaskNotFound(id))?
+            .checklist
+            .get(index)
+        {
+            Some(item) => item,
+            None => return Ok(()),
+        };
+        self.record(format!("toggle '{}'", item.text));
+
+        if let Some(item) = self.find_mut(id)?.checklist.get_mut(index) {
+            item.done = !item.done;
+            self.mark_dirty();
+        }
+
+        Ok(())
+    }
+
+    /// Move a task one place up or down among the tasks listed on a tab, by swapping it with its
+    /// listed neighbour in the file order. Returns false when it is already at the edge.
+    pub fn move_task(
+        &mut self,
+        id: usize,
+        tab: MenuItem,
+        view: &ListView,
+        up: bool,
+    ) -> Result<bool, LocalError> {
+        self.check_writable()?;
+
+        let listed: Vec<usize> = self
+            .tasks_for_tab(tab, view)
+            .iter()
+            .map(|task| task.id)
+            .collect();
+        let listed_index = listed
+            .iter()
+            .position(|listed_id| *listed_id == id)
+            .ok_or(LocalError::TaskNotFound(id))?;
+
+        let neighbour = match up {
+            true => listed_index.checked_sub(1).and_then(|i| listed.get(i)),
+            false => listed.get(listed_index + 1),
+        };
+        let neighbour = match neighbour {
+            Some(neighbour) => *neighbour,
+            None => return Ok(false),
+        };
+
+        let position = |id: usize| self.tasks.iter().position(|task| task.id == id);
+        let (from, to) = match (position(id), position(neighbour)) {
+            (Some(from), Some(to)) => (from, to),
+            _ => return Err(LocalError::TaskNotFound(id)),
+        };
+
+        self.record(format!("move '{}'", self.tasks[from].name));
+        self.tasks.swap(from, to);
+        self.mark_dirty();
+
+        Ok(true)
+    }
+
+    pub fn remove(&mut self, id: usize) -> Result<Task, LocalError> {
+        self.check_writable()?;
+
+        let index = self
+            .tasks
+            .iter()
+            .position(|task| task.id == id)
+            .ok_or(LocalError::TaskNotFound(id))?;
+        self.record(format!("delete '{}'", self.tasks[index].name));
+        self.mark_dirty();
+
+        Ok(self.tasks.remove(index))
+    }
+
+    pub fn notes(&self) -> &[Note] {
+        &self.notes
+    }
+
+    pub fn add_note(&mut self, mut note: Note) -> Result<usize, LocalError> {
+        self.check_writable()?;
+
+        self.record(format!("add note '{}'", note.title));
+        note.id = self.allocate_id();
+        let id = note.id;
+        self.notes.push(note);
+        self.mark_dirty();
+
+        Ok(id)
+    }
+
+    /// Apply the note form to the note with the matching ID and bump its modified time
+    pub fn update_note(&mut self, id: usize, form: &NoteForm) -> Result<bool, LocalError> {
+        self.check_writable()?;
+
+        let before = self.snapshot();
+        let note = self
+            .notes
+            .iter_mut()
+            .find(|note| note.id == id)
+            .ok_or(LocalError::NoteNotFound(id))?;
+        let title = note.title.clone();
+
+        let updated = form.apply_to(note);
+        if updated {
+            self.history
+                .record(format!("edit note '{}'", title), before);
+            self.mark_dirty();
+        }
+
+        Ok(updated)
+    }
+
+    pub fn remove_note(&mut self, id: usize) -> Result<Note, LocalError> {
+        self.check_writable()?;
+
+        let index = self
+            .notes
+            .iter()
+            .position(|note| note.id == id)
+            .ok_or(LocalError::NoteNotFound(id))?;
+        self.record(format!("delete note '{}'", self.notes[index].title));
+        self.mark_dirty();
+
+        Ok(self.notes.remove(index))
+    }
+
+    /// Revert the last change, returns what was undone or None when there is nothing to undo
+    pub fn undo(&mut self) -> Result<Option<String>, LocalError> {
+        self.check_writable()?;
+
+        let current = self.snapshot();
+        Ok(self
+            .history
+            .undo(current)
+            .map(|(description, before)| self.restore(description, before)))
+    }
+
+    /// Apply the last undone change again, returns what was redone
+    pub fn redo(&mut self) -> Result<Option<String>, LocalError> {
+        self.check_writable()?;
+
+        let current = self.snapshot();
+        Ok(self
+            .history
+            .redo(current)
+            .map(|(description, after)| self.restore(description, after)))
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            tasks: self.tasks.clone(),
+            notes: self.notes.clone(),
+        }
+    }
+
+    fn restore(&mut self, description: String, snapshot: Snapshot) -> String {
+        self.tasks = snapshot.tasks;
+        self.notes = snapshot.notes;
+        self.mark_dirty();
+
+        description
+    }
+
+    /// Remember the current state before a change so it can be undone
+    fn record(&mut self, description: String) {
+        let before = self.snapshot();
+        self.history.record(description, before);
+    }
+
+    fn find_mut(&mut self, id: usize) -> Result<&mut Task, LocalError> {
+        self.tasks
+            .iter_mut()
+            .find(|task| task.id == id)
+            .ok_or(LocalError::TaskNotFound(id))
+    }
+
+    fn check_writable(&self) -> Result<(), LocalError> {
+        match &self.load_error {
+            Some(_) => Err(LocalError::NotLoaded),
+            None => Ok(()),
+        }
+    }
+
+    /// Hand out the next task ID.
+    ///
+    /// The counter is persisted so an ID is never reused, even after the task holding the highest
+    /// ID is deleted. It is also never allowed to fall behind the IDs that are already in the
+    /// database, so a missing or stale counter file cannot produce a duplicate.
+    fn allocate_id(&mut self) -> usize {
+        let ids = self.tasks.iter().map(|task| task.id);
+        let id = self.next_id.max(next_free_id(
+            ids.chain(self.notes.iter().map(|note| note.id)),
+        ));
+        self.next_id = id + 1;
+
+        id
+    }
+}
+
+/// Smallest ID that is higher than every ID given
+fn next_free_id(ids: impl Iterator<Item = usize>) -> usize {
+    ids.map(|id| id + 1).max().unwrap_or(0)
+}
+
+/// IDs of every task and note
+fn snapshot_ids(snapshot: &Snapshot) -> impl Iterator<Item = usize> + '_ {
+    let tasks = snapshot.tasks.iter().map(|task| task.id);
+
+    tasks.chain(snapshot.notes.iter().map(|note| note.id))
+}
+
+/// Copy of `snapshot` with the IDs in `ids` replaced
+fn renumber(snapshot: &Snapshot, ids: &HashMap<usize, usize>) -> Snapshot {
+    let mut snapshot = snapshot.clone();
+
+    for task in &mut snapshot.tasks {
+        task.id = ids.get(&task.id).copied().unwrap_or(task.id);
+    }
+    for note in &mut snapshot.notes {
+        note.id = ids.get(&note.id).copied().unwrap_or(note.id);
+    }
+
+    snapshot
+}
+
+/// Put the changes `ours` made to `base` on top of `theirs`, see `merge_by_id`
+fn merge_snapshots(base: &Snapshot, ours: &Snapshot, theirs: &Snapshot) -> Snapshot {
+    Snapshot {
+        tasks: merge_by_id(&base.tasks, &ours.tasks, &theirs.tasks, |task| task.id),
+        notes: merge_by_id(&base.notes, &ours.notes, &theirs.notes, |note| note.id),
+    }
+}
+
+/// Three way merge of a list by ID. `base` is the list both sides started from.
+///
+/// An item changed in `ours` wins, even when `theirs` deleted it. An item deleted in `ours` is
+/// only dropped when `theirs` left it alone. Everything else is taken from `theirs`, in its
+/// order, with the items added in `ours` at the end.
+fn merge_by_id<'a, T: Clone + PartialEq>(
+    base: &'a [T],
+    ours: &'a [T],
+    theirs: &'a [T],
+    id: fn(&T) -> usize,
+) -> Vec<T> {
+    let find = |list: &'a [T], wanted: usize| list.iter().find(|item| id(item) == wanted);
+
+    let mut merged: Vec<T> = theirs
+        .iter()
+        .filter_map(|item| match (find(base, id(item)), find(ours, id(item))) {
+            (Some(before), Some(mine)) if mine != before => Some(mine.clone()),
+            (Some(before), None) if item == before => None,
+            _ => Some(item.clone()),
+        })
+        .collect();
+
+    merged.extend(
+        ours.iter()
+            .filter(|mine| find(theirs, id(mine)).is_none())
+            .filter(|mine| find(base, id(mine)).is_none_or(|before| *mine != before))
+            .cloned(),
+    );
+
+    merged
+}
+
+/// Give every task that shares its ID with an earlier task a fresh one.
+/// Returns true when anything had to be changed.
+fn fix_duplicate_ids(data: &mut [Task], next_id: &mut usize) -> bool {
+    let mut seen = HashSet::new();
+    let mut duplicates = Vec::new();
+
+    for (index, task) in data.iter().enumerate() {
+        if !seen.insert(task.id) {
+            duplicates.push(index);
+        }
+    }
+
+    for index in &duplicates {
+        let id = (*next_id).max(next_free_id(data.iter().map(|task| task.id)));
+        data[*index].id = id;
+        *next_id = id + 1;
+    }
+
+    !duplicates.is_empty()
+}
+
+/// What the database file looked like when it was last read or written
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+    hash: u64,
+}
+
+impl FileStamp {
+    /// Stamp of the file as it is now, `None` when it cannot be read
+    fn of(db_path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(db_path).ok()?;
+        let content = fs::read(db_path).ok()?;
+
+        Some(Self::new(&metadata, &content))
+    }
+
+    fn new(metadata: &Metadata, content: &[u8]) -> Self {
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+
+        Self {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+            hash: hasher.finish(),
+        }
+    }
+
+    /// Whether size and modification time are unchanged, without reading the file
+    fn matches(&self, metadata: &Metadata) -> bool {
+        self.len == metadata.len() && self.modified == metadata.modified().ok()
+    }
+}
+
+fn read_next_id(db_path: &Path) -> usize {
+    fs::read_to_string(config::next_id_path(db_path))
+        .ok()
+        .and_then(|raw| raw.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+fn write_next_id(db_path: &Path, next_id: usize) -> Result<(), LocalError> {
+    write_atomically(
+        &config::next_id_path(db_path),
+        next_id.to_string().as_bytes(),
+    )
+}
+
+/// Read the database and whether the file had to be migrated to the current schema
+fn read_database(db_path: &Path) -> Result<(Database, bool), LocalError> {
+    let raw_content = fs::read_to_string(db_path).map_err(|e| LocalError::from_read(db_path, e))?;
+
+    parse_database(db_path, &raw_content)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use super::*;
+
+    /// An empty database in a directory of its own under the system temp directory
+    fn temp_db(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("toodles-store-{}-{}", process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join("db.json");
+        fs::write(&path, serialize_database(&[], &[])).unwrap();
+        path
+    }
+
+    fn task(name: &str) -> Task {
+        Task {
+            name: name.to_string(),
+            ..Task::default()
+        }
+    }
+
+    /// A loaded store holding tasks with the given IDs and ID counter
+    fn store_with(ids: &[usize], next_id: usize) -> TaskStore {
+        TaskStore {
+            tasks: ids
+                .iter()
+                .map(|id| Task {
+                    id: *id,
+                    ..task("existing")
+                })
+                .collect(),
+            next_id,
+            load_error: None,
+            ..TaskStore::unloaded(&LocalError::NotLoaded)
+        }
+    }
+
+    #[test]
+    fn ids_are_not_reused_after_a_delete() {
+        let mut store = store_with(&[], 0);
+
+        let first = store.add(task("first")).unwrap();
+        let second = store.add(task("second")).unwrap();
+        store.remove(second).unwrap();
+        let third = store.add(task("third")).unwrap();
+
+        assert_eq!((first, second, third), (0, 1, 2));
+    }
+
+    #[test]
+    fn missing_or_stale_counter_does_not_reuse_ids() {
+        assert_eq!(store_with(&[0, 1, 2], 0).add(task("new")).unwrap(), 3);
+        assert_eq!(store_with(&[0, 1, 2], 1).add(task("new")).unwrap(), 3);
+    }
+
+    #[test]
+    fn duplicate_ids_are_repaired() {
+        let mut tasks: Vec<Task> = [4, 7, 4, 7, 4]
+            .iter()
+            .map(|id| Task {
+                id: *id,
+                ..task("duplicate")
+            })
+            .collect();
+        let mut next_id = 0;
+
+        assert!(fix_duplicate_ids(&mut tasks, &mut next_id));
+
+        let ids: Vec<usize> = tasks.iter().map(|task| task.id).collect();
+        assert_eq!(ids, vec![4, 7, 8, 9, 10]);
+        assert_eq!(next_id, 11);
+        assert!(!fix_duplicate_ids(&mut tasks, &mut next_id));
+    }
+
+    #[test]
+    fn saving_an_unchanged_task_is_not_a_change() {
+        let path = temp_db("unchanged-edit");
+        let mut store = TaskStore::open(&path).unwrap();
+        let id = store.add(task("as it was")).unwrap();
+        store.flush().unwrap();
+
+        let form = TaskForm::from_task(store.find(id).unwrap());
+
+        assert!(!store.update(id, &form).unwrap());
+        assert!(!store.dirty);
+        assert_eq!(store.undo().unwrap(), Some("add 'as it was'".to_string()));
+    }
+
+    #[test]
+    fn failed_write_is_reported_once() {
+        let path = temp_db("failed-write");
+        let mut store = TaskStore::open(&path).unwrap();
+        store.add(task("unsaved")).unwrap();
+
+        // nothing can be written once the directory is gone
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+        assert!(store.flush().is_err());
+        // the main loop flushes on every pass, that does not report the failure again
+        assert!(store.flush().is_ok());
+
+        // the next change tries again
+        store.add(task("also unsaved")).unwrap();
+        assert!(store.flush().is_err());
+        assert!(store.flush().is_ok());
+
+        // the changes are not given up, quitting tries once more
+        assert!(store.flush_on_exit().is_err());
+    }
+
+    #[test]
+    fn changes_from_another_store_are_picked_up() {
+        let path = temp_db("reload");
+        let mut ui = TaskStore::open(&path).unwrap();
+        let mut cli = TaskStore::open(&path).unwrap();
+
+        assert!(!ui.reload_if_changed().unwrap());
+
+        cli.add(task("from the cli")).unwrap();
+        cli.flush().unwrap();
+
+        assert!(ui.reload_if_changed().unwrap());
+        assert_eq!(ui.tasks()[0].name, "from the cli");
+        // the next ID follows the other store, so the IDs cannot collide
+        assert_eq!(ui.add(task("from the ui")).unwrap(), 1);
+    }
+
+    #[test]
+    fn rewriting_the_same_content_is_not_a_change() {
+        let path = temp_db("same-content");
+        let mut ui = TaskStore::open(&path).unwrap();
+
+        fs::write(&path, fs::read(&path).unwrap()).unwrap();
+
+        assert!(!ui.reload_if_changed().unwrap());
+        assert!(!ui.changed_on_disk());
+    }
+
+    #[test]
+    fn changes_from_another_store_are_merged_with_pending_ones() {
+        let path = temp_db("conflict");
+        let mut setup = TaskStore::open(&path).unwrap();
+        for name in [
+            "done in the cli",
+            "done in the ui",
+            "cli deletes",
+            "ui deletes",
+        ] {
+            setup.add(task(name)).unwrap();
+        }
+        setup.flush().unwrap();
+
+        let mut ui = TaskStore::open(&path).unwrap();
+        let mut cli = TaskStore::open(&path).unwrap();
+
+        cli.toggle_complete(0).unwrap();
+        cli.remove(2).unwrap();
+        cli.add(task("cli adds")).unwrap();
+        cli.flush().unwrap();
+
+        ui.toggle_complete(1).unwrap();
+        ui.remove(3).unwrap();
+        // gets the same ID as the task the cli added
+        ui.add(task("ui adds")).unwrap();
+
+        assert!(matches!(ui.flush(), Err(LocalError::ChangedOnDisk(_))));
+        ui.flush().unwrap();
+
+        let on_disk = TaskStore::open(&path).unwrap();
+        let tasks: Vec<(usize, &str, bool)> = on_disk
+            .tasks()
+            .iter()
+            .map(|task| (task.id, task.name.as_str(), task.complete))
+            .collect();
+        assert_eq!(
+            tasks,
+            vec![
+                (0, "done in the cli", true),
+                (1, "done in the ui", true),
+                (4, "cli adds", false),
+                (5, "ui adds", false),
+            ]
+        );
+
+        // undoing in the ui leaves the changes of the cli alone
+        ui.undo().unwrap();
+        ui.undo().unwrap();
+        let names: Vec<&str> = ui.tasks().iter().map(|task| task.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "done in the cli",
+                "done in the ui",
+                "ui deletes",
+                "cli adds"
+            ]
+        );
+        assert!(ui.tasks()[0].complete);
+    }
+}
@@ -0,0 +1,70 @@
+/// Parse the tags typed into the task form.
+///
+/// Tags are separated by spaces or commas. A tag starts with `#` (project) or `@` (context),
+/// bare words are taken as `#` tags. Tags are compared case-insensitively and kept lowercase,
+/// duplicates are dropped.
+pub fn parse_tags(input: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+
+    for word in input.split(|c: char| c.is_whitespace() || c == ',') {
+        let word = word.to_lowercase();
+
+        let tag = match word.strip_prefix(['#', '@']) {
+            Some("") => continue,
+            Some(_) => word,
+            None if word.is_empty() => continue,
+            None => format!("#{}", word),
+        };
+
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+
+    tags
+}
+
+/// Tags as they are pre-filled in the edit form
+pub fn format_tags(tags: &[String]) -> String {
+    tags.join(" ")
+}
+
+/// Which of `slots` colours a tag is drawn in, derived from its name so it is stable between runs
+pub fn tag_slot(tag: &str, slots: usize) -> usize {
+    let hash = tag.bytes().fold(0usize, |hash, byte| {
+        hash.wrapping_mul(31).wrapping_add(byte as usize)
+    });
+
+    hash % slots.max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags_keep_their_prefix() {
+        assert_eq!(parse_tags("#backend @home"), vec!["#backend", "@home"]);
+    }
+
+    #[test]
+    fn bare_words_become_project_tags() {
+        assert_eq!(
+            parse_tags("backend, Frontend"),
+            vec!["#backend", "#frontend"]
+        );
+    }
+
+    #[test]
+    fn duplicates_and_empty_tags_are_dropped() {
+        assert_eq!(parse_tags("#a  #A,, # @ @b @b"), vec!["#a", "@b"]);
+        assert!(parse_tags("  ").is_empty());
+    }
+
+    #[test]
+    fn colours_are_stable() {
+        assert_eq!(tag_slot("#backend", 6), tag_slot("#backend", 6));
+        assert!(tag_slot("#backend", 6) < 6);
+        assert_eq!(tag_slot("#backend", 0), 0);
+    }
+}
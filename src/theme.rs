@@ -0,0 +1,465 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use serde::Deserialize;
+use tui::style::{Color, Modifier, Style};
+
+use crate::tags::tag_slot;
+
+/// Colours every render function reads, so the whole UI can be switched at once
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    /// Plain text and the borders of unfocused blocks
+    pub text: Color,
+    /// Border of the block or form field that has focus
+    pub focus: Color,
+    /// Selected row of a list
+    pub highlight_fg: Color,
+    pub highlight_bg: Color,
+    /// Hints, counts and timestamps
+    pub muted: Color,
+    /// Characters matched by the search and other things that should stand out
+    pub accent: Color,
+    /// First letter of a tab name, the key that used to pick it
+    pub tab_key: Color,
+    pub tab_text: Color,
+    pub tab_selected: Color,
+    pub footer: Color,
+    pub footer_border: Color,
+    pub completed: Color,
+    pub overdue: Color,
+    pub due_today: Color,
+    /// Priority 7 and up
+    pub priority_high: Color,
+    /// Priority 4 to 6
+    pub priority_medium: Color,
+    /// Priority 1 to 3, 0 is drawn as plain text
+    pub priority_low: Color,
+    pub error: Color,
+    pub success: Color,
+    /// Backgrounds of tag chips, each tag always gets the same one
+    pub tags: Vec<Color>,
+    /// Text on tag chips
+    pub tag_text: Color,
+}
+
+impl Theme {
+    /// The colours toodles always had
+    pub fn dark() -> Self {
+        Self {
+            name: "dark".to_string(),
+            text: Color::White,
+            focus: Color::Yellow,
+            highlight_fg: Color::Black,
+            highlight_bg: Color::Yellow,
+            muted: Color::DarkGray,
+            accent: Color::Cyan,
+            tab_key: Color::Blue,
+            tab_text: Color::Cyan,
+            tab_selected: Color::Yellow,
+            footer: Color::Magenta,
+            footer_border: Color::DarkGray,
+            completed: Color::DarkGray,
+            overdue: Color::Red,
+            due_today: Color::Yellow,
+            priority_high: Color::LightRed,
+            priority_medium: Color::LightYellow,
+            priority_low: Color::LightGreen,
+            error: Color::Red,
+            success: Color::Green,
+            tags: vec![
+                Color::LightBlue,
+                Color::LightGreen,
+                Color::LightMagenta,
+                Color::LightCyan,
+                Color::LightYellow,
+                Color::LightRed,
+            ],
+            tag_text: Color::Black,
+        }
+    }
+
+    /// For terminals with a light background
+    pub fn light() -> Self {
+        Self {
+            name: "light".to_string(),
+            text: Color::Black,
+            focus: Color::Blue,
+            highlight_fg: Color::White,
+            highlight_bg: Color::Blue,
+            muted: Color::Gray,
+            accent: Color::Magenta,
+            tab_key: Color::Blue,
+            tab_text: Color::Black,
+            tab_selected: Color::Magenta,
+            footer: Color::Magenta,
+            footer_border: Color::Gray,
+            completed: Color::Gray,
+            overdue: Color::Red,
+            due_today: Color::Rgb(176, 112, 0),
+            priority_high: Color::Red,
+            priority_medium: Color::Rgb(176, 112, 0),
+            priority_low: Color::Green,
+            error: Color::Red,
+            success: Color::Green,
+            tags: vec![
+                Color::Blue,
+                Color::Green,
+                Color::Magenta,
+                Color::Cyan,
+                Color::Rgb(176, 112, 0),
+                Color::Red,
+            ],
+            tag_text: Color::White,
+        }
+    }
+
+    /// Bright colours only, nothing dimmed below readability
+    pub fn high_contrast() -> Self {
+        Self {
+            name: "high-contrast".to_string(),
+            text: Color::White,
+            focus: Color::LightYellow,
+            highlight_fg: Color::Black,
+            highlight_bg: Color::White,
+            muted: Color::Gray,
+            accent: Color::LightCyan,
+            tab_key: Color::LightYellow,
+            tab_text: Color::White,
+            tab_selected: Color::LightCyan,
+            footer: Color::White,
+            footer_border: Color::White,
+            completed: Color::Gray,
+            overdue: Color::LightRed,
+            due_today: Color::LightYellow,
+            priority_high: Color::LightRed,
+            priority_medium: Color::LightYellow,
+            priority_low: Color::LightGreen,
+            error: Color::LightRed,
+            success: Color::LightGreen,
+            tags: vec![
+                Color::LightYellow,
+                Color::LightCyan,
+                Color::LightGreen,
+                Color::LightMagenta,
+                Color::White,
+            ],
+            tag_text: Color::Black,
+        }
+    }
+
+    pub fn text(&self) -> Style {
+        Style::default().fg(self.text)
+    }
+
+    /// Border style of a block, highlighted while it has focus
+    pub fn border(&self, focused: bool) -> Style {
+        Style::default().fg(if focused { self.focus } else { self.text })
+    }
+
+    pub fn muted(&self) -> Style {
+        Style::default().fg(self.muted)
+    }
+
+    /// Selected row of a list
+    pub fn highlight(&self) -> Style {
+        Style::default()
+            .bg(self.highlight_bg)
+            .fg(self.highlight_fg)
+            .add_modifier(Modifier::BOLD)
+    }
+
+    pub fn completed(&self) -> Style {
+        Style::default()
+            .fg(self.completed)
+            .add_modifier(Modifier::CROSSED_OUT)
+    }
+
+    pub fn priority(&self, priority: usize) -> Style {
+        match priority {
+            0 => self.text(),
+            1..=3 => Style::default().fg(self.priority_low),
+            4..=6 => Style::default().fg(self.priority_medium),
+            _ => Style::default().fg(self.priority_high),
+        }
+    }
+
+    /// Colour a tag is drawn in, in the sidebar and as the background of its chip
+    pub fn tag(&self, tag: &str) -> Color {
+        self.tags
+            .get(tag_slot(tag, self.tags.len()))
+            .copied()
+            .unwrap_or(self.accent)
+    }
+
+    pub fn tag_chip(&self, tag: &str) -> Style {
+        Style::default().bg(self.tag(tag)).fg(self.tag_text)
+    }
+
+    /// Set one colour by the name it has in the theme file. `tags` takes a list of colours
+    /// separated by commas.
+    fn set(&mut self, field: &str, value: &str) -> Result<(), String> {
+        if field == "tags" {
+            self.tags = value
+                .split(',')
+                .map(parse_color)
+                .collect::<Result<_, _>>()?;
+            return Ok(());
+        }
+
+        let color = parse_color(value)?;
+        let slot = match field {
+            "text" => &mut self.text,
+            "focus" => &mut self.focus,
+            "highlight_fg" => &mut self.highlight_fg,
+            "highlight_bg" => &mut self.highlight_bg,
+            "muted" => &mut self.muted,
+            "accent" => &mut self.accent,
+            "tab_key" => &mut self.tab_key,
+            "tab_text" => &mut self.tab_text,
+            "tab_selected" => &mut self.tab_selected,
+            "footer" => &mut self.footer,
+            "footer_border" => &mut self.footer_border,
+            "completed" => &mut self.completed,
+            "overdue" => &mut self.overdue,
+            "due_today" => &mut self.due_today,
+            "priority_high" => &mut self.priority_high,
+            "priority_medium" => &mut self.priority_medium,
+            "priority_low" => &mut self.priority_low,
+            "error" => &mut self.error,
+            "success" => &mut self.success,
+            "tag_text" => &mut self.tag_text,
+            _ => return Err(format!("unknown colour '{}'", field)),
+        };
+
+        *slot = color;
+        Ok(())
+    }
+}
+
+/// Parse a colour from the theme file: a name like `red` or `light-blue`, `#rrggbb`, or a
+/// 256 colour index
+pub fn parse_color(input: &str) -> Result<Color, String> {
+    let name = input.trim().to_lowercase().replace(['-', '_', ' '], "");
+
+    if let Some(hex) = name.strip_prefix('#') {
+        let channel = |range| u8::from_str_radix(hex.get(range)?, 16).ok();
+
+        return match (hex.len(), channel(0..2), channel(2..4), channel(4..6)) {
+            (6, Some(r), Some(g), Some(b)) => Ok(Color::Rgb(r, g, b)),
+            _ => Err(format!("invalid colour '{}'", input)),
+        };
+    }
+
+    if let Ok(index) = name.parse() {
+        return Ok(Color::Indexed(index));
+    }
+
+    Ok(match name.as_str() {
+        "reset" | "default" => Color::Reset,
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" | "grey" => Color::Gray,
+        "darkgray" | "darkgrey" => Color::DarkGray,
+        "lightred" => Color::LightRed,
+        "lightgreen" => Color::LightGreen,
+        "lightyellow" => Color::LightYellow,
+        "lightblue" => Color::LightBlue,
+        "lightmagenta" => Color::LightMagenta,
+        "lightcyan" => Color::LightCyan,
+        "white" => Color::White,
+        _ => return Err(format!("invalid colour '{}'", input)),
+    })
+}
+
+/// Theme file as it is written: the theme to start with, then theme name to colour name to
+/// colour
+#[derive(Deserialize)]
+struct ThemeConfig {
+    theme: Option<String>,
+    #[serde(flatten)]
+    themes: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+/// The built-in themes followed by the user's, and the one in use
+pub struct Themes {
+    themes: Vec<Theme>,
+    current: usize,
+}
+
+impl Default for Themes {
+    fn default() -> Self {
+        Self {
+            themes: vec![Theme::dark(), Theme::light(), Theme::high_contrast()],
+            current: 0,
+        }
+    }
+}
+
+impl Themes {
+    /// The built-in themes plus the ones from the theme file.
+    ///
+    /// Every table in the file is a theme named after it. It starts as a copy of the theme named
+    /// by its `base` key, `dark` without one, and overrides the colours it lists. A theme named
+    /// like a built-in one replaces it. A `theme = "<name>"` key at the top picks the theme to
+    /// start with. A missing file just means the built-in themes; problems in the file are
+    /// returned to be reported at startup.
+    pub fn load(path: &Path) -> (Self, Vec<String>) {
+        let mut themes = Themes::default();
+
+        let raw = match fs::read_to_string(path) {
+            Ok(raw) => raw,
+            Err(_) => return (themes, Vec::new()),
+        };
+
+        let problems = match toml::from_str::<ThemeConfig>(&raw) {
+            Ok(config) => themes.apply(&config),
+            Err(e) => vec![format!("cannot read {}: {}", path.display(), e)],
+        };
+
+        (themes, problems)
+    }
+
+    fn apply(&mut self, config: &ThemeConfig) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        for (name, colors) in &config.themes {
+            let mut theme = match colors.get("base") {
+                Some(base) => match self.find(base) {
+                    Some(theme) => theme.clone(),
+                    None => {
+                        problems.push(format!("unknown base theme '{}' for {}", base, name));
+                        Theme::dark()
+                    }
+                },
+                None => Theme::dark(),
+            };
+            theme.name = name.clone();
+
+            for (field, value) in colors.iter().filter(|(field, _)| *field != "base") {
+                if let Err(e) = theme.set(field, value) {
+                    problems.push(format!("{} in theme {}", e, name));
+                }
+            }
+
+            match self.themes.iter_mut().find(|other| other.name == *name) {
+                Some(existing) => *existing = theme,
+                None => self.themes.push(theme),
+            }
+        }
+
+        if let Some(name) = &config.theme {
+            match self.themes.iter().position(|theme| theme.name == *name) {
+                Some(index) => self.current = index,
+                None => problems.push(format!("unknown theme '{}' to start with", name)),
+            }
+        }
+
+        problems
+    }
+
+    fn find(&self, name: &str) -> Option<&Theme> {
+        self.themes.iter().find(|theme| theme.name == name)
+    }
+
+    pub fn current(&self) -> &Theme {
+        &self.themes[self.current]
+    }
+
+    /// Switch to the next theme, wrapping around, and return it
+    pub fn next(&mut self) -> &Theme {
+        self.current = (self.current + 1) % self.themes.len();
+        self.current()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(toml: &str) -> ThemeConfig {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn colours_by_name_hex_and_index() {
+        assert_eq!(parse_color("Light-Blue"), Ok(Color::LightBlue));
+        assert_eq!(parse_color("dark_grey"), Ok(Color::DarkGray));
+        assert_eq!(parse_color("#ff8000"), Ok(Color::Rgb(255, 128, 0)));
+        assert_eq!(parse_color("208"), Ok(Color::Indexed(208)));
+        assert!(parse_color("#ff80").is_err());
+        assert!(parse_color("blurple").is_err());
+    }
+
+    #[test]
+    fn user_themes_start_from_their_base() {
+        let mut themes = Themes::default();
+        let problems = themes.apply(&config("[paper]\nbase = \"light\"\naccent = \"red\"\n"));
+
+        assert!(problems.is_empty());
+        let paper = themes.find("paper").unwrap();
+        assert_eq!(paper.accent, Color::Red);
+        assert_eq!(paper.text, Theme::light().text);
+    }
+
+    #[test]
+    fn user_themes_can_replace_built_in_ones() {
+        let mut themes = Themes::default();
+        themes.apply(&config("[dark]\nfooter = \"white\"\n"));
+
+        assert_eq!(themes.themes.len(), 3);
+        assert_eq!(themes.current().footer, Color::White);
+    }
+
+    #[test]
+    fn problems_are_reported() {
+        let mut themes = Themes::default();
+        let problems = themes.apply(&config(
+            "[odd]\nbase = \"nope\"\nshiny = \"red\"\ntext = \"blurple\"\n",
+        ));
+
+        assert_eq!(problems.len(), 3);
+        assert!(themes.find("odd").is_some());
+    }
+
+    #[test]
+    fn next_wraps_around() {
+        let mut themes = Themes::default();
+
+        assert_eq!(themes.next().name, "light");
+        assert_eq!(themes.next().name, "high-contrast");
+        assert_eq!(themes.next().name, "dark");
+    }
+
+    #[test]
+    fn start_theme_is_picked_after_loading_user_themes() {
+        let mut themes = Themes::default();
+        let problems = themes.apply(&config("theme = \"paper\"\n[paper]\nbase = \"light\"\n"));
+
+        assert!(problems.is_empty());
+        assert_eq!(themes.current().name, "paper");
+
+        let problems = themes.apply(&config("theme = \"sepia\"\n"));
+        assert_eq!(problems, vec!["unknown theme 'sepia' to start with"]);
+        assert_eq!(themes.current().name, "paper");
+    }
+
+    #[test]
+    fn tag_colours_come_from_the_theme() {
+        let mut themes = Themes::default();
+        let problems = themes.apply(&config(
+            "[dark]\ntags = \"red, #00ff00\"\ntag_text = \"white\"\n",
+        ));
+
+        assert!(problems.is_empty());
+        let dark = themes.current();
+        assert_eq!(dark.tags, vec![Color::Red, Color::Rgb(0, 255, 0)]);
+        assert!(dark.tags.contains(&dark.tag("#backend")));
+        assert_eq!(dark.tag_chip("#backend").fg, Some(Color::White));
+    }
+}
@@ -1,159 +1,752 @@
-use std::{sync::mpsc::Receiver, time::Duration};
+use std::{path::PathBuf, sync::mpsc::Receiver, time::Duration};
 
+use chrono::prelude::Local;
 use tui::widgets::ListState;
 
 use crate::{
-    read_database, remove_selected_task_from_list, MenuItem, SHOW_POPUP, TERMINATION_LOCK,
+    backup,
+    dashboard::{home_entries, EntryKind},
+    expire_status,
+    export::{export_to_file, ExportFormat},
+    form::{FormAction, NoteField, NoteForm, TaskForm},
+    keymap::{Action, Layer},
+    modal::{self, ConfirmAction, Modal},
+    show_error, show_status,
+    store::TaskStore,
+    view::{ListView, SortMode},
+    App, LocalError, MenuItem, Note, TaskListStates, TERMINATION_LOCK,
 };
 
 use crate::Event;
 
-use crossterm::event::{self, KeyCode};
+use crossterm::event::{self, KeyCode, KeyEvent};
 
-pub fn handle_keys(
-    rx: &Receiver<Event<event::KeyEvent>>,
+/// Lines a note is scrolled per PageUp / PageDown
+const NOTE_SCROLL_STEP: u16 = 5;
 
-    active_menu_item: &mut MenuItem,
-    todo_list_state: &mut ListState,
-) {
+pub fn handle_keys(rx: &Receiver<Event<event::KeyEvent>>, app: &mut App) {
     if let Ok(event) = rx.recv_timeout(Duration::from_millis(500)) {
-        if *SHOW_POPUP.lock().unwrap() {
-            // Handle keycodes for popups!!
-
-            match active_menu_item {
-                MenuItem::Home => todo!(),
-                MenuItem::Todo => match event {
-                    Event::Input(event) => match event.code {
-                        KeyCode::Char('q') => {
-                            *SHOW_POPUP.lock().unwrap() = false;
-                        }
-                        KeyCode::Char('n') => {}
-                        _ => {}
-                    },
-                    Event::Tick => {}
-                },
-                MenuItem::Done => todo!(),
-                MenuItem::All => todo!(),
-                MenuItem::Notes => todo!(),
-                MenuItem::Quit => todo!(),
-            }
-        } else {
-            // Handle default keys here!!
-            match active_menu_item {
-                MenuItem::Home => match event {
-                    Event::Input(event) => match event.code {
-                        KeyCode::Left | KeyCode::Char('h') => active_menu_item.previous(),
-                        KeyCode::Right | KeyCode::Char('l') => active_menu_item.next(),
-                        KeyCode::Char('q' | 'Q') => {
-                            let mut x = TERMINATION_LOCK.lock().unwrap();
-                            *x = true;
-                            drop(x);
-                        }
-                        // Menu context sensitive items are shown here!!
-                        _ => {}
-                    },
-                    Event::Tick => {}
+        if let Event::Tick = event {
+            expire_status();
+
+            if let Err(e) = reload_changed_database(app) {
+                show_error(&e);
+            }
+        }
+
+        // the topmost popup takes every key until it is closed
+        let result = match (modal::top(), event) {
+            (Some(popup), Event::Input(key)) => handle_modal_keys(popup, key, app),
+            (Some(_), Event::Tick) => Ok(()),
+            (None, event) => handle_event(event, app),
+        };
+
+        if let Err(e) = result {
+            show_error(&e);
+        }
+    }
+}
+
+/// Pick up changes another toodles, like a CLI command, made to the database
+fn reload_changed_database(app: &mut App) -> Result<(), LocalError> {
+    if app.store.reload_if_changed()? {
+        show_status("The database was changed outside, reloaded it".to_string());
+        clamp_selections(
+            &app.store,
+            &app.view,
+            &mut app.list_states,
+            &mut app.note_list_state,
+        );
+        app.checklist_focus = None;
+        app.note_scroll = 0;
+    }
+
+    Ok(())
+}
+
+/// Keys for the popup on top of the stack
+fn handle_modal_keys(popup: Modal, key: KeyEvent, app: &mut App) -> Result<(), LocalError> {
+    match popup {
+        Modal::TaskForm => handle_task_form_keys(key, app),
+        Modal::NoteForm => handle_note_form_keys(key, app),
+        Modal::Confirm { action, .. } => match app.keymap.mode_action(Layer::Confirm, &key) {
+            Some(Action::Yes) => {
+                modal::close();
+                confirm(action, app)
+            }
+            Some(Action::No) => {
+                modal::close();
+                Ok(())
+            }
+            _ => Ok(()),
+        },
+        Modal::Export => match app.keymap.mode_action(Layer::Export, &key) {
+            Some(Action::Cancel) => {
+                modal::close();
+                Ok(())
+            }
+            action => match action.and_then(Action::export_format) {
+                Some(format) => {
+                    modal::close();
+                    export_list(format, app)
+                }
+                None => Ok(()),
+            },
+        },
+        // any key dismisses an error or the help
+        Modal::Error(_) | Modal::Help => {
+            modal::close();
+            Ok(())
+        }
+    }
+}
+
+fn handle_task_form_keys(key: KeyEvent, app: &mut App) -> Result<(), LocalError> {
+    let action = app.keymap.mode_action(Layer::Form, &key);
+    let App {
+        store,
+        active_menu_item,
+        list_states,
+        task_form,
+        view,
+        ..
+    } = app;
+
+    match task_form.handle_key(key, action) {
+        // an invalid form is not saved, it stays open showing what is wrong
+        FormAction::Submit if !task_form.validate() => {}
+        FormAction::Submit => {
+            let saved = match task_form.editing {
+                Some(id) => {
+                    // saving an unchanged task just closes the form
+                    store.update(id, task_form)?;
+                    Some(id)
+                }
+                None => match task_form.to_task() {
+                    Some(task) => Some(store.add(task)?),
+                    None => None,
                 },
-                MenuItem::Todo => {
-                    match event {
-                        Event::Input(event) => match event.code {
-                            KeyCode::Left | KeyCode::Char('h') => active_menu_item.previous(),
-                            KeyCode::Right | KeyCode::Char('l') => active_menu_item.next(),
-                            KeyCode::Char('q' | 'Q') => {
-                                let mut x = TERMINATION_LOCK.lock().unwrap();
-                                *x = true;
-                                drop(x);
-                            }
-                            // Menu context sensitive items are shown here!!
-                            KeyCode::Char('a' | 'A') => {
-                                let mut x = SHOW_POPUP.lock().unwrap();
-                                *x = true;
-                                drop(x);
-                            }
-                            KeyCode::Char('d' | 'D') => {
-                                remove_selected_task_from_list(todo_list_state)
-                                    .expect("Cannot remove item!!")
-                            }
-
-                            KeyCode::Down | KeyCode::Char('j') => {
-                                if let Some(selected) = todo_list_state.selected() {
-                                    let amount_of_todos =
-                                        read_database().expect("Cannot read DB").len();
-
-                                    if selected >= amount_of_todos - 1 {
-                                        todo_list_state.select(Some(0))
-                                    } else {
-                                        todo_list_state.select(Some(selected + 1));
-                                    }
-                                }
-                            }
-                            KeyCode::Up | KeyCode::Char('k') => {
-                                if let Some(selected) = todo_list_state.selected() {
-                                    let amount_of_todos =
-                                        read_database().expect("Cannot read DB").len();
-
-                                    if selected > 0 {
-                                        todo_list_state.select(Some(selected - 1));
-                                    } else {
-                                        todo_list_state.select(Some(amount_of_todos - 1));
-                                    }
-                                }
-                            }
-                            _ => {}
-                        },
-                        Event::Tick => {}
-                    }
+            };
+
+            if let Some(id) = saved {
+                let verb = if task_form.editing.is_some() {
+                    "Saved"
+                } else {
+                    "Added"
+                };
+                show_status(format!("{} '{}'", verb, task_form.name.value.trim()));
+
+                // the task can move when the list is sorted, follow it in the open list
+                if let Some(list_state) = list_states.for_tab(*active_menu_item) {
+                    select_task(store, view, *active_menu_item, list_state, id);
                 }
-                MenuItem::Done => {
-                    match event {
-                        Event::Input(event) => match event.code {
-                            KeyCode::Left | KeyCode::Char('h' | 'H') => active_menu_item.previous(),
-                            KeyCode::Right | KeyCode::Char('l' | 'L') => active_menu_item.next(),
-                            KeyCode::Char('q' | 'Q') => {
-                                let mut x = TERMINATION_LOCK.lock().unwrap();
-                                *x = true;
-                                drop(x);
-                            }
-                            // Menu context sensitive items are shown here!!
-                            _ => {}
-                        },
-                        Event::Tick => {}
-                    }
+                task_form.clear();
+                modal::close();
+            }
+        }
+        FormAction::Cancel => {
+            task_form.clear();
+            modal::close();
+        }
+        FormAction::None => {}
+    }
+
+    Ok(())
+}
+
+fn handle_note_form_keys(key: KeyEvent, app: &mut App) -> Result<(), LocalError> {
+    let kind_action = match app.note_form.focus {
+        NoteField::Kind => app.keymap.mode_action(Layer::NoteKind, &key),
+        _ => None,
+    };
+    let action = kind_action.or_else(|| app.keymap.mode_action(Layer::Form, &key));
+    let App {
+        store,
+        note_list_state,
+        note_form,
+        note_scroll,
+        ..
+    } = app;
+
+    match note_form.handle_key(key, action) {
+        FormAction::Submit if !note_form.validate() => {}
+        FormAction::Submit => {
+            let saved = match note_form.editing {
+                Some(id) => {
+                    // saving an unchanged note just closes the form
+                    store.update_note(id, note_form)?;
+                    Some(id)
                 }
-                MenuItem::All => {
-                    match event {
-                        Event::Input(event) => match event.code {
-                            KeyCode::Left | KeyCode::Char('h' | 'H') => active_menu_item.previous(),
-                            KeyCode::Right | KeyCode::Char('l' | 'L') => active_menu_item.next(),
-                            KeyCode::Char('q' | 'Q') => {
-                                let mut x = TERMINATION_LOCK.lock().unwrap();
-                                *x = true;
-                                drop(x);
-                            }
-                            // Menu context sensitive items are shown here!!
-                            _ => {}
-                        },
-                        Event::Tick => {}
-                    }
+                None => match note_form.to_note() {
+                    Some(note) => Some(store.add_note(note)?),
+                    None => None,
+                },
+            };
+
+            if let Some(id) = saved {
+                let verb = if note_form.editing.is_some() {
+                    "Saved"
+                } else {
+                    "Added"
+                };
+                show_status(format!("{} note '{}'", verb, note_form.title.value.trim()));
+                select_note(store, note_list_state, id);
+                *note_scroll = 0;
+                note_form.clear();
+                modal::close();
+            }
+        }
+        FormAction::Cancel => {
+            note_form.clear();
+            modal::close();
+        }
+        FormAction::None => {}
+    }
+
+    Ok(())
+}
+
+/// Export the list of the active tab as it is shown into a file in the working directory,
+/// named after the tab
+fn export_list(format: ExportFormat, app: &App) -> Result<(), LocalError> {
+    let tasks = app.store.tasks_for_tab(app.active_menu_item, &app.view);
+    let path = PathBuf::from(format!(
+        "toodles-{}.{}",
+        app.active_menu_item.to_string().to_lowercase(),
+        format.extension()
+    ));
+
+    export_to_file(&tasks, format, &path)?;
+    show_status(format!(
+        "Exported {} tasks as {} to {}",
+        tasks.len(),
+        format,
+        path.display()
+    ));
+
+    Ok(())
+}
+
+/// Carry out a confirmed action
+fn confirm(action: ConfirmAction, app: &mut App) -> Result<(), LocalError> {
+    match action {
+        ConfirmAction::DeleteTask(id) => {
+            let task = app.store.remove(id)?;
+            show_status(format!("Deleted '{}'", task.name));
+
+            if let Some(list_state) = app.list_states.for_tab(app.active_menu_item) {
+                if let Some(index) = list_state.selected() {
+                    list_state.select(Some(index.saturating_sub(1)));
                 }
-                MenuItem::Notes => {
-                    match event {
-                        Event::Input(event) => match event.code {
-                            KeyCode::Left | KeyCode::Char('h' | 'H') => active_menu_item.previous(),
-                            KeyCode::Right | KeyCode::Char('l' | 'L') => active_menu_item.next(),
-                            KeyCode::Char('q' | 'Q') => {
-                                let mut x = TERMINATION_LOCK.lock().unwrap();
-                                *x = true;
-                                drop(x);
-                            }
-                            // Menu context sensitive items are shown here!!
-                            _ => {}
-                        },
-                        Event::Tick => {}
-                    }
+            }
+        }
+        ConfirmAction::DeleteNote(id) => {
+            let note = app.store.remove_note(id)?;
+            show_status(format!("Deleted note '{}'", note.title));
+
+            if let Some(index) = app.note_list_state.selected() {
+                app.note_list_state.select(Some(index.saturating_sub(1)));
+            }
+            app.note_scroll = 0;
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_event(event: Event<event::KeyEvent>, app: &mut App) -> Result<(), LocalError> {
+    let key = match event {
+        Event::Input(key) => key,
+        Event::Tick => return Ok(()),
+    };
+
+    let App {
+        store,
+        active_menu_item,
+        list_states,
+        task_form,
+        view,
+        checklist_focus,
+        home_list_state,
+        note_list_state,
+        note_form,
+        note_scroll,
+        keymap,
+        themes,
+    } = app;
+
+    // while searching keys go into the search box of the task list
+    if let Some(list_state) = list_states.for_tab(*active_menu_item) {
+        if view.searching {
+            let action = keymap.mode_action(Layer::Search, &key);
+            handle_search_keys(key.code, action, store, view, *active_menu_item, list_state);
+            return Ok(());
+        }
+    }
+
+    // the checklist key moves focus into the checklist of the selected task and keys then act on it
+    if let Some(list_state) = list_states.for_tab(*active_menu_item) {
+        if checklist_focus.is_some() {
+            return handle_checklist_keys(
+                keymap.mode_action(Layer::Checklist, &key),
+                store,
+                view,
+                *active_menu_item,
+                list_state,
+                checklist_focus,
+            );
+        }
+    }
+
+    let action = match keymap.action(*active_menu_item, &key) {
+        Some(action) => action,
+        None => return Ok(()),
+    };
+
+    match action {
+        Action::NextTab => active_menu_item.next(),
+        Action::PrevTab => active_menu_item.previous(),
+        Action::Quit => *TERMINATION_LOCK.lock().unwrap() = true,
+        Action::NextTheme => show_status(format!("Theme: {}", themes.next().name)),
+        Action::Help => modal::open(Modal::Help),
+        // a database that failed to load can be swapped for the latest good backup
+        Action::RestoreBackup => {
+            if store.load_error().is_some() {
+                backup::restore_latest_backup()?;
+                *store = TaskStore::load()?;
+            }
+        }
+        Action::Undo | Action::Redo => {
+            step_history(action, store, view, list_states, note_list_state)?
+        }
+        Action::AddTask => {
+            task_form.clear();
+            modal::open(Modal::TaskForm);
+        }
+        // the selected task of whichever task list is open
+        Action::EditTask | Action::DeleteTask => {
+            let tab = *active_menu_item;
+            let selected = list_states
+                .for_tab(tab)
+                .and_then(|list_state| store.selected_task(list_state, tab, view));
+
+            match selected {
+                Some(task) if action == Action::EditTask => {
+                    *task_form = TaskForm::from_task(task);
+                    modal::open(Modal::TaskForm);
                 }
-                MenuItem::Quit => unreachable!(),
+                Some(task) => modal::open(Modal::Confirm {
+                    message: format!("Delete '{}'?", task.name),
+                    action: ConfirmAction::DeleteTask(task.id),
+                }),
+                None => {}
             }
         }
+        Action::AddNote => {
+            note_form.clear();
+            modal::open(Modal::NoteForm);
+        }
+        Action::EditNote => {
+            if let Some(note) = selected_note(store, note_list_state) {
+                *note_form = NoteForm::from_note(note);
+                modal::open(Modal::NoteForm);
+            }
+        }
+        Action::DeleteNote => {
+            if let Some(note) = selected_note(store, note_list_state) {
+                modal::open(Modal::Confirm {
+                    message: format!("Delete note '{}'?", note.title),
+                    action: ConfirmAction::DeleteNote(note.id),
+                });
+            }
+        }
+        // scrolling past the end of the note is undone when it is drawn
+        Action::ScrollDown => *note_scroll = note_scroll.saturating_add(NOTE_SCROLL_STEP),
+        Action::ScrollUp => *note_scroll = note_scroll.saturating_sub(NOTE_SCROLL_STEP),
+        action if *active_menu_item == MenuItem::Home => handle_home_keys(
+            action,
+            store,
+            view,
+            active_menu_item,
+            list_states,
+            home_list_state,
+        ),
+        action => match (*active_menu_item, list_states.for_tab(*active_menu_item)) {
+            (_, Some(list_state)) => handle_task_list_keys(
+                action,
+                store,
+                view,
+                *active_menu_item,
+                list_state,
+                checklist_focus,
+            )?,
+            (MenuItem::Notes, None) => {
+                handle_note_list_keys(action, store, note_list_state, note_scroll)
+            }
+            _ => {}
+        },
     }
+
+    Ok(())
+}
+
+/// Actions shared by every tab that shows a list of tasks: moving the selection, toggling
+/// completion and changing how the list is sorted and filtered
+fn handle_task_list_keys(
+    action: Action,
+    store: &mut TaskStore,
+    view: &mut ListView,
+    tab: MenuItem,
+    list_state: &mut ListState,
+    checklist_focus: &mut Option<usize>,
+) -> Result<(), LocalError> {
+    let change_view: Option<fn(&mut ListView)> = match action {
+        Action::CycleSort => Some(ListView::next_sort),
+        Action::RaisePriorityFilter => Some(ListView::raise_min_priority),
+        Action::LowerPriorityFilter => Some(ListView::lower_min_priority),
+        Action::ToggleOpenOnly => Some(ListView::toggle_only_incomplete),
+        // leaving a finished search shows the whole list again
+        Action::ClearSearch => Some(|view: &mut ListView| view.search.clear()),
+        Action::ClearTag => Some(ListView::clear_tag),
+        _ => None,
+    };
+
+    if let Some(change_view) = change_view {
+        change_view_keeping_selection(store, view, tab, list_state, change_view);
+        return Ok(());
+    }
+
+    match action {
+        Action::Search => {
+            view.searching = true;
+            return Ok(());
+        }
+        Action::Export => {
+            modal::open(Modal::Export);
+            return Ok(());
+        }
+        // steps through the tags of the sidebar
+        Action::NextTag => {
+            let tags: Vec<String> = store.tag_counts().into_iter().map(|(tag, _)| tag).collect();
+            change_view_keeping_selection(store, view, tab, list_state, |view| {
+                view.next_tag(&tags)
+            });
+            return Ok(());
+        }
+        Action::FocusChecklist => {
+            let has_checklist = store
+                .selected_task(list_state, tab, view)
+                .map(|task| !task.checklist.is_empty())
+                .unwrap_or(false);
+
+            if has_checklist {
+                *checklist_focus = Some(0);
+            }
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    let amount_of_todos = store.tasks_for_tab(tab, view).len();
+
+    if amount_of_todos == 0 {
+        return Ok(());
+    }
+
+    let selected = list_state.selected().unwrap_or(0).min(amount_of_todos - 1);
+
+    match action {
+        Action::Down => {
+            if selected >= amount_of_todos - 1 {
+                list_state.select(Some(0))
+            } else {
+                list_state.select(Some(selected + 1));
+            }
+        }
+        Action::Up => {
+            if selected > 0 {
+                list_state.select(Some(selected - 1));
+            } else {
+                list_state.select(Some(amount_of_todos - 1));
+            }
+        }
+        // the list only holds matches while a search is active, so the next match is the next row
+        Action::NextMatch if !view.search.is_empty() => {
+            list_state.select(Some((selected + 1) % amount_of_todos));
+        }
+        Action::PrevMatch if !view.search.is_empty() => {
+            list_state.select(Some((selected + amount_of_todos - 1) % amount_of_todos));
+        }
+        // moving tasks changes the file order, so it only makes sense while the list shows it
+        Action::MoveUp | Action::MoveDown if view.sort != SortMode::Unsorted => {
+            show_status(format!(
+                "Tasks can only be moved in file order, the list is sorted by {}",
+                view.sort
+            ));
+        }
+        Action::MoveUp | Action::MoveDown => {
+            let id = store.tasks_for_tab(tab, view)[selected].id;
+
+            if store.move_task(id, tab, view, action == Action::MoveUp)? {
+                select_task(store, view, tab, list_state, id);
+            }
+        }
+        Action::ToggleComplete => {
+            let id = store.tasks_for_tab(tab, view)[selected].id;
+            store.toggle_complete(id)?;
+
+            // the task can leave the list, keep the selection in range
+            let remaining = store.tasks_for_tab(tab, view).len();
+            if selected >= remaining {
+                list_state.select(Some(remaining.saturating_sub(1)));
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Moving through the overview on Home. Opening an entry jumps to its task on the Todo tab, or on
+/// the Done tab for completed ones, dropping filters that would hide it.
+fn handle_home_keys(
+    action: Action,
+    store: &TaskStore,
+    view: &mut ListView,
+    active_menu_item: &mut MenuItem,
+    list_states: &mut TaskListStates,
+    list_state: &mut ListState,
+) {
+    let entries = home_entries(store.tasks(), Local::now());
+
+    if entries.is_empty() {
+        return;
+    }
+
+    let selected = list_state.selected().unwrap_or(0).min(entries.len() - 1);
+
+    match action {
+        Action::Down => list_state.select(Some((selected + 1) % entries.len())),
+        Action::Up => list_state.select(Some((selected + entries.len() - 1) % entries.len())),
+        Action::OpenTask => {
+            let id = entries[selected].id;
+            let tab = match entries[selected].kind {
+                EntryKind::Completed => MenuItem::Done,
+                _ => MenuItem::Todo,
+            };
+
+            if !store
+                .tasks_for_tab(tab, view)
+                .iter()
+                .any(|task| task.id == id)
+            {
+                view.clear_filters();
+            }
+
+            let tab_state = list_states
+                .for_tab(tab)
+                .expect("Task tabs always have a list state");
+            select_task(store, view, tab, tab_state, id);
+            *active_menu_item = tab;
+        }
+        _ => {}
+    }
+}
+
+/// Change how the list is sorted or filtered and keep the selection on the same task
+fn change_view_keeping_selection(
+    store: &TaskStore,
+    view: &mut ListView,
+    tab: MenuItem,
+    list_state: &mut ListState,
+    change_view: impl FnOnce(&mut ListView),
+) {
+    let selected_id = store
+        .selected_task(list_state, tab, view)
+        .map(|task| task.id);
+
+    change_view(view);
+
+    match selected_id {
+        Some(id) => select_task(store, view, tab, list_state, id),
+        None => list_state.select(Some(0)),
+    }
+}
+
+/// Undo or redo the last change, from every tab
+fn step_history(
+    action: Action,
+    store: &mut TaskStore,
+    view: &ListView,
+    list_states: &mut TaskListStates,
+    note_list_state: &mut ListState,
+) -> Result<(), LocalError> {
+    let (stepped, verb) = match action {
+        Action::Redo => (store.redo()?, "redo"),
+        _ => (store.undo()?, "undo"),
+    };
+
+    match stepped {
+        Some(description) if verb == "undo" => show_status(format!("Undid {}", description)),
+        Some(description) => show_status(format!("Redid {}", description)),
+        None => show_status(format!("Nothing to {}", verb)),
+    }
+
+    // the lists can be shorter now
+    clamp_selections(store, view, list_states, note_list_state);
+
+    Ok(())
+}
+
+/// Keep the selection of every list in range after the lists changed underneath it
+fn clamp_selections(
+    store: &TaskStore,
+    view: &ListView,
+    list_states: &mut TaskListStates,
+    note_list_state: &mut ListState,
+) {
+    for tab in [MenuItem::Todo, MenuItem::Done, MenuItem::All] {
+        let amount_of_todos = store.tasks_for_tab(tab, view).len();
+        let list_state = list_states
+            .for_tab(tab)
+            .expect("Task tabs always have a list state");
+
+        clamp_selection(list_state, amount_of_todos);
+    }
+    clamp_selection(note_list_state, store.notes().len());
+}
+
+/// Keep a list selection inside a list of `len` entries
+fn clamp_selection(list_state: &mut ListState, len: usize) {
+    let selected = list_state.selected().unwrap_or(0);
+
+    list_state.select(Some(selected.min(len.saturating_sub(1))));
+}
+
+/// Point the selection at the task with the given ID, or at the top when it is not listed
+fn select_task(
+    store: &TaskStore,
+    view: &ListView,
+    tab: MenuItem,
+    list_state: &mut ListState,
+    id: usize,
+) {
+    let index = store
+        .tasks_for_tab(tab, view)
+        .iter()
+        .position(|task| task.id == id)
+        .unwrap_or(0);
+
+    list_state.select(Some(index));
+}
+
+/// Moving through the checklist of the selected task and toggling its items, with the keys of
+/// the checklist layer
+fn handle_checklist_keys(
+    action: Option<Action>,
+    store: &mut TaskStore,
+    view: &ListView,
+    tab: MenuItem,
+    list_state: &ListState,
+    checklist_focus: &mut Option<usize>,
+) -> Result<(), LocalError> {
+    let task = match store.selected_task(list_state, tab, view) {
+        Some(task) if !task.checklist.is_empty() => task,
+        _ => {
+            *checklist_focus = None;
+            return Ok(());
+        }
+    };
+
+    let id = task.id;
+    let amount_of_items = task.checklist.len();
+    let selected = checklist_focus.unwrap_or(0).min(amount_of_items - 1);
+
+    match action {
+        Some(Action::Back) => *checklist_focus = None,
+        Some(Action::Down) => *checklist_focus = Some((selected + 1) % amount_of_items),
+        Some(Action::Up) => {
+            *checklist_focus = Some((selected + amount_of_items - 1) % amount_of_items)
+        }
+        Some(Action::ToggleItem) => store.toggle_checklist_item(id, selected)?,
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Typing into the search box narrows the list live. The keys of the search layer keep the
+/// search or drop it.
+fn handle_search_keys(
+    code: KeyCode,
+    action: Option<Action>,
+    store: &TaskStore,
+    view: &mut ListView,
+    tab: MenuItem,
+    list_state: &mut ListState,
+) {
+    let selected_id = store
+        .selected_task(list_state, tab, view)
+        .map(|task| task.id);
+
+    match (action, code) {
+        (Some(Action::KeepSearch), _) => view.searching = false,
+        (Some(Action::ClearSearch), _) => {
+            view.searching = false;
+            view.search.clear();
+        }
+        (Some(_), _) => return,
+        (None, KeyCode::Backspace) => {
+            view.search.pop();
+        }
+        (None, KeyCode::Char(c)) => view.search.push(c),
+        (None, _) => return,
+    }
+
+    match selected_id {
+        Some(id) => select_task(store, view, tab, list_state, id),
+        None => list_state.select(Some(0)),
+    }
+}
+
+fn selected_note<'a>(store: &'a TaskStore, list_state: &ListState) -> Option<&'a Note> {
+    list_state
+        .selected()
+        .and_then(|index| store.notes().get(index))
+}
+
+/// Point the selection at the note with the given ID
+fn select_note(store: &TaskStore, list_state: &mut ListState, id: usize) {
+    let index = store
+        .notes()
+        .iter()
+        .position(|note| note.id == id)
+        .unwrap_or(0);
+
+    list_state.select(Some(index));
+}
+
+/// Moving through the notes, a different note always starts scrolled to the top
+fn handle_note_list_keys(
+    action: Action,
+    store: &TaskStore,
+    list_state: &mut ListState,
+    scroll: &mut u16,
+) {
+    let amount_of_notes = store.notes().len();
+
+    if amount_of_notes == 0 {
+        return;
+    }
+
+    let selected = list_state.selected().unwrap_or(0).min(amount_of_notes - 1);
+
+    let next = match action {
+        Action::Down => (selected + 1) % amount_of_notes,
+        Action::Up => (selected + amount_of_notes - 1) % amount_of_notes,
+        _ => return,
+    };
+
+    list_state.select(Some(next));
+    *scroll = 0;
 }
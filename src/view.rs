@@ -0,0 +1,299 @@
+use std::cmp::Reverse;
+
+use strum_macros::Display;
+
+use crate::{search::task_score, Task};
+
+/// Order of the task lists, cycled with `s`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Display)]
+pub enum SortMode {
+    #[strum(serialize = "file order")]
+    Unsorted,
+    #[strum(serialize = "priority")]
+    Priority,
+    #[strum(serialize = "newest")]
+    Newest,
+    #[strum(serialize = "oldest")]
+    Oldest,
+    #[strum(serialize = "name")]
+    Alphabetical,
+    #[strum(serialize = "due date")]
+    DueDate,
+}
+
+impl SortMode {
+    fn next(&mut self) {
+        match self {
+            SortMode::Unsorted => *self = SortMode::Priority,
+            SortMode::Priority => *self = SortMode::Newest,
+            SortMode::Newest => *self = SortMode::Oldest,
+            SortMode::Oldest => *self = SortMode::Alphabetical,
+            SortMode::Alphabetical => *self = SortMode::DueDate,
+            SortMode::DueDate => *self = SortMode::Unsorted,
+        }
+    }
+}
+
+/// Highest priority the minimum priority filter can be raised to
+const MAX_PRIORITY_FILTER: usize = 9;
+
+/// How the task lists are sorted and filtered, shared by the Todo, Done and All tabs
+#[derive(Clone, Debug)]
+pub struct ListView {
+    pub sort: SortMode,
+    /// Only show tasks with at least this priority
+    pub min_priority: usize,
+    pub only_incomplete: bool,
+    /// Only show tasks carrying this tag
+    pub tag: Option<String>,
+    /// Fuzzy search over the name, substring search over the description, empty when not
+    /// searching
+    pub search: String,
+    /// Whether keys are currently typed into the search instead of acting on the list
+    pub searching: bool,
+}
+
+impl Default for ListView {
+    fn default() -> Self {
+        Self {
+            sort: SortMode::Unsorted,
+            min_priority: 0,
+            only_incomplete: false,
+            tag: None,
+            search: String::new(),
+            searching: false,
+        }
+    }
+}
+
+impl ListView {
+    pub fn next_sort(&mut self) {
+        self.sort.next();
+    }
+
+    pub fn raise_min_priority(&mut self) {
+        self.min_priority = (self.min_priority + 1).min(MAX_PRIORITY_FILTER);
+    }
+
+    pub fn lower_min_priority(&mut self) {
+        self.min_priority = self.min_priority.saturating_sub(1);
+    }
+
+    pub fn toggle_only_incomplete(&mut self) {
+        self.only_incomplete = !self.only_incomplete;
+    }
+
+    /// Step the tag filter to the next of `tags`, after the last one the filter is removed
+    pub fn next_tag(&mut self, tags: &[String]) {
+        let next = match &self.tag {
+            Some(current) => tags
+                .iter()
+                .position(|tag| tag == current)
+                .and_then(|index| tags.get(index + 1)),
+            None => tags.first(),
+        };
+
+        self.tag = next.cloned();
+    }
+
+    pub fn clear_tag(&mut self) {
+        self.tag = None;
+    }
+
+    /// Drop every filter and the search, the sort order stays
+    pub fn clear_filters(&mut self) {
+        *self = Self {
+            sort: self.sort,
+            ..Self::default()
+        };
+    }
+
+    /// Filter and sort tasks for display, ties keep their file order.
+    ///
+    /// While searching the best matches come first and the sort order only decides between
+    /// equally good ones.
+    pub fn apply<'a>(&self, tasks: impl Iterator<Item = &'a Task>) -> Vec<&'a Task> {
+        let mut tasks: Vec<(&Task, i64)> = tasks
+            .filter(|task| task.priority >= self.min_priority)
+            .filter(|task| !self.only_incomplete || !task.complete)
+            .filter(|task| {
+                self.tag
+                    .as_ref()
+                    .map(|tag| task.tags.contains(tag))
+                    .unwrap_or(true)
+            })
+            .filter_map(|task| {
+                if self.search.is_empty() {
+                    Some((task, 0))
+                } else {
+                    task_score(&self.search, task).map(|score| (task, score))
+                }
+            })
+            .collect();
+
+        match self.sort {
+            SortMode::Unsorted => {}
+            SortMode::Priority => tasks.sort_by_key(|(task, _)| Reverse(task.priority)),
+            SortMode::Newest => tasks.sort_by_key(|(task, _)| Reverse(task.created)),
+            SortMode::Oldest => tasks.sort_by_key(|(task, _)| task.created),
+            SortMode::Alphabetical => tasks.sort_by_key(|(task, _)| task.name.to_lowercase()),
+            // tasks without a due date go last
+            SortMode::DueDate => tasks.sort_by_key(|(task, _)| (task.due.is_none(), task.due)),
+        }
+
+        // stable, so equally good matches keep the order from above
+        tasks.sort_by_key(|(_, score)| Reverse(*score));
+
+        tasks.into_iter().map(|(task, _)| task).collect()
+    }
+
+    /// Short description of the active sort and filters for the list title
+    pub fn describe(&self) -> String {
+        let mut parts = if self.search.is_empty() {
+            vec![format!("by {}", self.sort)]
+        } else {
+            vec![format!("by match, then {}", self.sort)]
+        };
+
+        if self.min_priority > 0 {
+            parts.push(format!("prio >= {}", self.min_priority));
+        }
+
+        if self.only_incomplete {
+            parts.push("open only".to_string());
+        }
+
+        if let Some(tag) = &self.tag {
+            parts.push(tag.clone());
+        }
+
+        if self.searching {
+            parts.push(format!("/{}_", self.search));
+        } else if !self.search.is_empty() {
+            parts.push(format!("/{}", self.search));
+        }
+
+        parts.join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{prelude::Local, TimeZone};
+
+    use super::*;
+
+    fn task(id: usize, name: &str, priority: usize, complete: bool) -> Task {
+        Task {
+            id,
+            name: name.to_string(),
+            priority,
+            complete,
+            created: Local
+                .with_ymd_and_hms(2024, 5, id as u32 + 1, 9, 0, 0)
+                .unwrap(),
+            ..Task::default()
+        }
+    }
+
+    fn tasks() -> Vec<Task> {
+        vec![
+            task(0, "water plants", 2, false),
+            task(1, "file taxes", 8, false),
+            task(2, "book flights", 5, true),
+            task(3, "answer mail", 8, false),
+            task(4, "fix bike", 0, false),
+        ]
+    }
+
+    fn ids(view: &ListView, tasks: &[Task]) -> Vec<usize> {
+        view.apply(tasks.iter())
+            .iter()
+            .map(|task| task.id)
+            .collect()
+    }
+
+    #[test]
+    fn unsorted_and_unfiltered_keeps_file_order() {
+        assert_eq!(ids(&ListView::default(), &tasks()), vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn filters_apply_before_sorting_with_ties_in_file_order() {
+        let view = ListView {
+            sort: SortMode::Priority,
+            min_priority: 2,
+            only_incomplete: true,
+            ..ListView::default()
+        };
+
+        assert_eq!(ids(&view, &tasks()), vec![1, 3, 0]);
+    }
+
+    #[test]
+    fn tag_filter_and_name_sort() {
+        let mut tasks = tasks();
+        for index in [1, 3, 4] {
+            tasks[index].tags = vec!["#admin".to_string()];
+        }
+        let view = ListView {
+            sort: SortMode::Alphabetical,
+            tag: Some("#admin".to_string()),
+            ..ListView::default()
+        };
+
+        assert_eq!(ids(&view, &tasks), vec![3, 1, 4]);
+    }
+
+    #[test]
+    fn newest_first_among_open_tasks() {
+        let view = ListView {
+            sort: SortMode::Newest,
+            only_incomplete: true,
+            ..ListView::default()
+        };
+
+        assert_eq!(ids(&view, &tasks()), vec![4, 3, 1, 0]);
+    }
+
+    #[test]
+    fn search_ranks_matches_before_the_sort_order() {
+        let view = ListView {
+            sort: SortMode::Oldest,
+            search: "fi".to_string(),
+            ..ListView::default()
+        };
+
+        // "file taxes" and "fix bike" match at the start, "book flights" only further in
+        assert_eq!(ids(&view, &tasks()), vec![1, 4, 2]);
+    }
+
+    #[test]
+    fn due_date_sort_puts_tasks_without_one_last() {
+        let mut tasks = tasks();
+        tasks[3].due = Some(Local.with_ymd_and_hms(2024, 6, 2, 9, 0, 0).unwrap());
+        tasks[1].due = Some(Local.with_ymd_and_hms(2024, 6, 1, 9, 0, 0).unwrap());
+        let view = ListView {
+            sort: SortMode::DueDate,
+            min_priority: 1,
+            ..ListView::default()
+        };
+
+        assert_eq!(ids(&view, &tasks), vec![1, 3, 0, 2]);
+    }
+
+    #[test]
+    fn clearing_filters_keeps_the_sort() {
+        let mut view = ListView {
+            sort: SortMode::DueDate,
+            min_priority: 5,
+            search: "x".to_string(),
+            ..ListView::default()
+        };
+
+        view.clear_filters();
+
+        assert_eq!(view.sort, SortMode::DueDate);
+        assert_eq!(ids(&view, &tasks()), vec![0, 1, 2, 3, 4]);
+    }
+}